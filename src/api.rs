@@ -0,0 +1,473 @@
+/*! The public API for third-party frontends, under `/api/v1`. These types
+    are the wire format and are kept separate from the game state's own
+    types, so that the internals can change without breaking anyone. A
+    breaking change to anything here needs a new version alongside this
+    one. The API is described by an OpenAPI document served at
+    `/api/v1/openapi.json`; keep it in step with this file. The tests
+    below check these types against it.
+ */
+
+use rocket::http::RawStr;
+use rocket::request::FromParam;
+use rocket::response::content;
+use rocket_contrib::Json;
+
+use error::{ApiError, ApiResult};
+use events::{self, TimeoutKind};
+use gamestate::{ActiveClock, GameState};
+use gamestate::jamstate::{JamState, Team};
+use guard::{Game, MutGame};
+use {apply_command, record_penalty, UpdateCommand};
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum TeamV1 {
+    Home,
+    Away,
+}
+
+impl TeamV1 {
+    fn team(self) -> Team {
+        match self {
+            TeamV1::Home => Team::Home,
+            TeamV1::Away => Team::Away,
+        }
+    }
+
+    fn from_team(team: Team) -> TeamV1 {
+        match team {
+            Team::Home => TeamV1::Home,
+            Team::Away => TeamV1::Away,
+        }
+    }
+}
+
+impl<'a> FromParam<'a> for TeamV1 {
+    type Error = &'static str;
+
+    fn from_param(param: &'a RawStr) -> Result<Self, Self::Error> {
+        match param.as_str() {
+            "home" => Ok(TeamV1::Home),
+            "away" => Ok(TeamV1::Away),
+            _ => Err("Team must be home or away"),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct TeamStateV1 {
+    name: String,
+    color: Option<String>,
+    logo: Option<String>,
+    score: u32,
+    jam_score: u32,
+    timeouts: u8,
+    reviews: u8,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum ClockKindV1 {
+    Jam,
+    Lineup,
+    Timeout,
+    TeamTimeout,
+    Review,
+    Intermission,
+    TimeToDerby,
+    None,
+}
+
+#[derive(Serialize)]
+struct ClockV1 {
+    kind: ClockKindV1,
+    /// The team whose timeout or review it is.
+    team: Option<TeamV1>,
+    active_ms: u64,
+    active_running: bool,
+    active_counts_down: bool,
+    period_ms: u64,
+    period_running: bool,
+}
+
+#[derive(Serialize)]
+struct GameV1 {
+    /// Milliseconds since the epoch when this was made.
+    server_time: u64,
+    period: u8,
+    jam: u8,
+    clock: ClockV1,
+    home: TeamStateV1,
+    away: TeamStateV1,
+}
+
+#[derive(Serialize)]
+struct TeamJamV1 {
+    score: u32,
+    jammer_points: u32,
+    pivot_points: u32,
+    lead: bool,
+    lost: bool,
+    call: bool,
+    star_pass: bool,
+}
+
+#[derive(Serialize)]
+struct JamV1 {
+    period: u8,
+    jam: u8,
+    home: TeamJamV1,
+    away: TeamJamV1,
+}
+
+#[derive(Serialize)]
+struct PenaltyV1 {
+    code: char,
+    period: u8,
+    jam: u8,
+}
+
+#[derive(Serialize)]
+struct SkaterPenaltiesV1 {
+    number: String,
+    penalties: Vec<PenaltyV1>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum TimeoutKindV1 {
+    Team,
+    Official,
+    Review,
+}
+
+/// Commands, tagged by name, like `{"command": "team_timeout", "team":
+/// "home"}`. `at` is an optional press time in milliseconds of server time.
+#[derive(Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+enum CommandV1 {
+    StartJam { at: Option<u64> },
+    StopJam { at: Option<u64> },
+    OfficialTimeout,
+    TeamTimeout { team: TeamV1 },
+    OfficialReview { team: TeamV1 },
+    EndTimeout,
+    ConvertTimeout { kind: TimeoutKindV1, team: Option<TeamV1> },
+    ReviewOutcome { team: TeamV1, retained: bool },
+    StarPass { team: TeamV1 },
+    AdjustScore { home: i8, away: i8 },
+    SetScore { home: u32, away: u32 },
+    SetPeriodClock { seconds: u16 },
+    SetActiveClock { seconds: u16 },
+    SetPeriod { period: u8 },
+    SetJam { jam: u8 },
+    SetTimeouts { team: TeamV1, remaining: u8 },
+    SetReviews { team: TeamV1, remaining: u8 },
+}
+
+impl CommandV1 {
+    fn into_update(self) -> UpdateCommand {
+        match self {
+            CommandV1::StartJam { at: None } => UpdateCommand::start_jam,
+            CommandV1::StartJam { at: Some(at) } => UpdateCommand::start_jam_at(at),
+            CommandV1::StopJam { at: None } => UpdateCommand::stop_jam,
+            CommandV1::StopJam { at: Some(at) } => UpdateCommand::stop_jam_at(at),
+            CommandV1::OfficialTimeout => UpdateCommand::official_timeout,
+            CommandV1::TeamTimeout { team } => UpdateCommand::team_timeout(team.team()),
+            CommandV1::OfficialReview { team } => UpdateCommand::official_review(team.team()),
+            CommandV1::EndTimeout => UpdateCommand::end_timeout,
+            CommandV1::ConvertTimeout { kind, team } => {
+                let kind = match kind {
+                    TimeoutKindV1::Team => TimeoutKind::Team,
+                    TimeoutKindV1::Official => TimeoutKind::Official,
+                    TimeoutKindV1::Review => TimeoutKind::Review,
+                };
+                UpdateCommand::convert_timeout(kind, team.map(|t| t.team()))
+            },
+            CommandV1::ReviewOutcome { team, retained: true } =>
+                UpdateCommand::review_retained(team.team()),
+            CommandV1::ReviewOutcome { team, retained: false } =>
+                UpdateCommand::review_lost(team.team()),
+            CommandV1::StarPass { team } => UpdateCommand::star_pass(team.team()),
+            CommandV1::AdjustScore { home, away } => UpdateCommand::score_adj(home, away),
+            CommandV1::SetScore { home, away } => UpdateCommand::score_set(home, away),
+            CommandV1::SetPeriodClock { seconds } => UpdateCommand::set_time(seconds),
+            CommandV1::SetActiveClock { seconds } => UpdateCommand::set_active_time(seconds),
+            CommandV1::SetPeriod { period } => UpdateCommand::set_period(period),
+            CommandV1::SetJam { jam } => UpdateCommand::set_jam(jam),
+            CommandV1::SetTimeouts { team, remaining } =>
+                UpdateCommand::set_timeouts(team.team(), remaining),
+            CommandV1::SetReviews { team, remaining } =>
+                UpdateCommand::set_reviews(team.team(), remaining),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PenaltyCommandV1 {
+    skater: String,
+    code: char,
+}
+
+fn team_param(team: Result<TeamV1, &'static str>) -> ApiResult<Team> {
+    team.map(|t| t.team()).map_err(|e| ApiError::bad_request("bad_team", e))
+}
+
+fn team_state(game: &GameState, team: Team) -> TeamStateV1 {
+    let roster = game.roster(team);
+    let pick = |pair: (u32, u32)| if team == Team::Home { pair.0 } else { pair.1 };
+    let pick8 = |pair: (u8, u8)| if team == Team::Home { pair.0 } else { pair.1 };
+    let cur_jam = game.cur_jam();
+    let jam_score = if cur_jam.played() {
+        cur_jam.jam_score()
+    } else {
+        game.prev_jam().map_or((0, 0), |j| j.jam_score())
+    };
+    TeamStateV1 {
+        name: roster.name.clone(), color: roster.color.clone(), logo: roster.logo.clone(),
+        score: pick(game.total_score()), jam_score: pick(jam_score),
+        timeouts: pick8(game.timeouts()), reviews: pick8(game.reviews()),
+    }
+}
+
+fn clock(game: &GameState) -> ClockV1 {
+    let (kind, team) = match game.get_active_clock() {
+        ActiveClock::jam(..) => (ClockKindV1::Jam, None),
+        ActiveClock::lineup(_) => (ClockKindV1::Lineup, None),
+        ActiveClock::timeout(_) => (ClockKindV1::Timeout, None),
+        ActiveClock::team_timeout(t, _) => (ClockKindV1::TeamTimeout, Some(TeamV1::from_team(t))),
+        ActiveClock::review(t, _) => (ClockKindV1::Review, Some(TeamV1::from_team(t))),
+        ActiveClock::intermission(_) => (ClockKindV1::Intermission, None),
+        ActiveClock::time_to_derby(_) => (ClockKindV1::TimeToDerby, None),
+        ActiveClock::none => (ClockKindV1::None, None),
+    };
+    let state = game.clock_state();
+    ClockV1 {
+        kind: kind, team: team,
+        active_ms: state.active_ms, active_running: state.active_running,
+        active_counts_down: state.active_counts_down,
+        period_ms: state.game_ms, period_running: state.game_running,
+    }
+}
+
+fn team_jam(jam: &JamState, team: Team) -> TeamJamV1 {
+    let t = &jam[team];
+    TeamJamV1 {
+        score: t.points(),
+        jammer_points: t.jammer_points(), pivot_points: t.pivot_points(),
+        lead: t.lead(), lost: t.lost(), call: t.call(), star_pass: t.starpass(),
+    }
+}
+
+fn game_v1(game: &GameState) -> GameV1 {
+    GameV1 {
+        server_time: events::now_millis(),
+        period: game.get_time().0, jam: game.jamnum(), clock: clock(game),
+        home: team_state(game, Team::Home), away: team_state(game, Team::Away),
+    }
+}
+
+#[get("/api/v1/game")]
+fn get_game(game: Game) -> Json<GameV1> {
+    Json(game_v1(&game))
+}
+
+fn jams_v1(game: &GameState) -> Vec<JamV1> {
+    game.jams().iter().enumerate().map(|(idx, jam)| {
+        let (period, num) = game.jamidx_to_periodjam(idx);
+        JamV1 { period: period, jam: num,
+                home: team_jam(jam, Team::Home), away: team_jam(jam, Team::Away) }
+    }).collect()
+}
+
+#[get("/api/v1/jams")]
+fn get_jams(game: Game) -> Json<Vec<JamV1>> {
+    Json(jams_v1(&game))
+}
+
+#[post("/api/v1/commands", format = "application/json", data = "<cmd>")]
+fn post_command(mut game: MutGame, cmd: Json<CommandV1>) -> ApiResult<Json<GameV1>> {
+    apply_command(&mut game, cmd.0.into_update())?;
+    Ok(Json(game_v1(&game)))
+}
+
+fn penalties(game: &GameState, team: Team) -> Vec<SkaterPenaltiesV1> {
+    let mut by_skater = game.team_penalties(team).into_iter().map(|(number, penalties)| {
+        SkaterPenaltiesV1 {
+            number: number,
+            penalties: penalties.iter().map(|p| {
+                PenaltyV1 { code: p.code(), period: p.jam().0, jam: p.jam().1 }
+            }).collect(),
+        }
+    }).collect::<Vec<_>>();
+    by_skater.sort_by(|a, b| a.number.cmp(&b.number));
+    by_skater
+}
+
+#[get("/api/v1/penalties/<team>")]
+fn get_penalties(game: Game, team: Result<TeamV1, &'static str>)
+                 -> ApiResult<Json<Vec<SkaterPenaltiesV1>>> {
+    Ok(Json(penalties(&game, team_param(team)?)))
+}
+
+#[post("/api/v1/penalties/<team>", format = "application/json", data = "<cmd>")]
+fn post_penalty(mut game: MutGame, team: Result<TeamV1, &'static str>,
+                cmd: Json<PenaltyCommandV1>) -> ApiResult<Json<Vec<SkaterPenaltiesV1>>> {
+    let team = team_param(team)?;
+    record_penalty(&mut game, team, None, &cmd.skater, cmd.code)?;
+    Ok(Json(penalties(&game, team)))
+}
+
+#[get("/api/v1/openapi.json")]
+fn openapi() -> content::Json<&'static str> {
+    content::Json(include_str!("openapi.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use serde_json::{self, Value};
+    use gamestate::GameState;
+    use gamestate::jamstate::Team;
+    use gamestate::ruleset::Ruleset;
+    use roster;
+    use super::{game_v1, jams_v1, CommandV1, PenaltyCommandV1, PenaltyV1, SkaterPenaltiesV1};
+
+    fn spec() -> Value {
+        serde_json::from_str(include_str!("openapi.json")).unwrap()
+    }
+
+    fn schema<'a>(spec: &'a Value, name: &str) -> &'a Value {
+        &spec["components"]["schemas"][name]
+    }
+
+    fn resolve<'a>(spec: &'a Value, schema: &'a Value) -> &'a Value {
+        match schema.get("$ref").and_then(|r| r.as_str()) {
+            Some(r) => resolve(spec, spec.pointer(&r[1..]).unwrap()),
+            None => schema,
+        }
+    }
+
+    /// Check a value against a schema, as far as the schemas in
+    /// openapi.json go. Objects may not have properties the schema
+    /// doesn't list.
+    fn check(spec: &Value, schema: &Value, value: &Value, path: &str) -> Result<(), String> {
+        let schema = resolve(spec, schema);
+        if value.is_null() {
+            return if schema["nullable"] == Value::Bool(true) { Ok(()) }
+                   else { Err(format!("{} is null", path)) };
+        }
+        if let Some(all) = schema.get("allOf").and_then(|a| a.as_array()) {
+            return all.iter().map(|s| check(spec, s, value, path)).collect();
+        }
+        if let Some(one) = schema.get("oneOf").and_then(|a| a.as_array()) {
+            return match one.iter().filter(|s| check(spec, s, value, path).is_ok()).count() {
+                1 => Ok(()),
+                n => Err(format!("{} matches {} of oneOf", path, n)),
+            };
+        }
+        if let Some(values) = schema.get("enum").and_then(|e| e.as_array()) {
+            if !values.contains(value) {
+                return Err(format!("{} is {}, not one of {:?}", path, value, values));
+            }
+        }
+        let ok = match schema["type"].as_str() {
+            Some("object") => {
+                let object = match value.as_object() {
+                    Some(o) => o,
+                    None => return Err(format!("{} is not an object", path)),
+                };
+                let properties = schema["properties"].as_object().unwrap();
+                for (key, v) in object {
+                    match properties.get(key) {
+                        Some(s) => check(spec, s, v, &format!("{}.{}", path, key))?,
+                        None => return Err(format!("{}.{} isn't in the schema", path, key)),
+                    }
+                }
+                for key in schema.get("required").and_then(|r| r.as_array())
+                                 .map_or(&[][..], |r| &r[..]) {
+                    if !object.contains_key(key.as_str().unwrap()) {
+                        return Err(format!("{} has no {}", path, key));
+                    }
+                }
+                true
+            },
+            Some("array") => match value.as_array() {
+                Some(items) => {
+                    for (i, v) in items.iter().enumerate() {
+                        check(spec, &schema["items"], v, &format!("{}[{}]", path, i))?;
+                    }
+                    true
+                },
+                None => false,
+            },
+            Some("integer") => value.is_i64() || value.is_u64(),
+            Some("string") => value.is_string(),
+            Some("boolean") => value.is_boolean(),
+            _ => true,
+        };
+        if ok { Ok(()) } else { Err(format!("{} is {}, not a {}", path, value, schema["type"])) }
+    }
+
+    /// A value for a schema, with every property filled in.
+    fn example(spec: &Value, schema: &Value) -> Value {
+        let schema = resolve(spec, schema);
+        if let Some(values) = schema.get("enum").and_then(|e| e.as_array()) {
+            return values[0].clone();
+        }
+        if let Some(all) = schema.get("allOf").and_then(|a| a.as_array()) {
+            return example(spec, &all[0]);
+        }
+        match schema["type"].as_str() {
+            Some("object") => Value::Object(schema["properties"].as_object().unwrap().iter()
+                .map(|(key, s)| (key.clone(), example(spec, s))).collect()),
+            Some("array") => Value::Array(vec![example(spec, &schema["items"])]),
+            Some("integer") => Value::from(1),
+            Some("string") => Value::from("X"),
+            Some("boolean") => Value::Bool(true),
+            _ => Value::Null,
+        }
+    }
+
+    fn new_game() -> GameState {
+        GameState::new(roster::Team::new(String::from("Home")),
+                       roster::Team::new(String::from("Away")),
+                       Duration::new(0, 0), Ruleset::default())
+    }
+
+    #[test]
+    fn test_responses_match_schema() {
+        let spec = spec();
+        let mut game = new_game();
+        game.start_jam();
+        game.cur_jam_mut().adj_score(3, 0);
+        game.cur_jam_mut()[Team::Home].set_starpass(true);
+
+        let value = serde_json::to_value(&game_v1(&game)).unwrap();
+        check(&spec, schema(&spec, "Game"), &value, "game").unwrap();
+        let items = serde_json::from_str::<Value>(
+            r##"{"type": "array", "items": {"$ref": "#/components/schemas/Jam"}}"##).unwrap();
+        let value = serde_json::to_value(&jams_v1(&game)).unwrap();
+        check(&spec, &items, &value, "jams").unwrap();
+        let penalties = SkaterPenaltiesV1 {
+            number: String::from("12"),
+            penalties: vec![PenaltyV1 { code: 'X', period: 1, jam: 1 }],
+        };
+        let value = serde_json::to_value(&penalties).unwrap();
+        check(&spec, schema(&spec, "SkaterPenalties"), &value, "penalties").unwrap();
+    }
+
+    #[test]
+    fn test_requests_match_schema() {
+        let spec = spec();
+        for variant in schema(&spec, "Command")["oneOf"].as_array().unwrap() {
+            let command = example(&spec, variant);
+            assert!(serde_json::from_value::<CommandV1>(command.clone()).is_ok(),
+                    "{} doesn't parse", command);
+        }
+        let unknown = serde_json::from_str::<Value>(r#"{"command": "not_a_command"}"#).unwrap();
+        assert!(serde_json::from_value::<CommandV1>(unknown).is_err());
+        let penalty = example(&spec, schema(&spec, "PenaltyCommand"));
+        assert!(serde_json::from_value::<PenaltyCommandV1>(penalty).is_ok());
+    }
+}
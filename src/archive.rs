@@ -0,0 +1,273 @@
+/*! The game archive. Whenever a game finishes, or is replaced by a new
+    one, it's saved to the `archive` subdirectory of the data directory as
+    its DerbyJSON document plus a little metadata for the list. Archived
+    games can be browsed at `/archive`, looked at read-only, and exported
+    again as DerbyJSON, which can also be fed back into `/import` to edit
+    them.
+ */
+
+use std::fs::{self, File};
+use std::io;
+use std::path::PathBuf;
+use std::thread;
+
+use chrono::{Local, TimeZone};
+use handlebars;
+use rocket::http::Status;
+use rocket::response::content;
+use rocket_contrib::Json;
+use serde_json::{self, Value};
+
+use config;
+use derbylive;
+use error::{ApiError, ApiResult};
+use events::{self, Event};
+use gamestate::GameState;
+use gamestate::jamstate::Team;
+use guard;
+use import;
+use reports;
+use staticpages;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ArchiveMeta {
+    pub id: String,
+    pub home: String,
+    pub away: String,
+    pub date: String,
+    pub score: (u32, u32),
+    /// Whether it was imported and edited rather than scored here.
+    pub edited: bool,
+}
+
+/// A game as it's kept in the archive.
+#[derive(Serialize, Deserialize)]
+pub struct ArchivedGame {
+    meta: ArchiveMeta,
+    /// The DerbyJSON game document.
+    game: Value,
+}
+
+fn archive_dir() -> PathBuf {
+    config::get().data_dir.join("archive")
+}
+
+/// Ids are the time the game was set up, so they sort by date.
+fn valid_id(id: &str) -> bool {
+    !id.is_empty() && id.len() <= 20 && id.chars().all(|c| c.is_digit(10))
+}
+
+fn meta(game: &GameState) -> ArchiveMeta {
+    let created = game.created();
+    let date = Local.timestamp((created / 1000) as i64, 0);
+    ArchiveMeta {
+        id: created.to_string(),
+        home: game.roster(Team::Home).name.clone(),
+        away: game.roster(Team::Away).name.clone(),
+        date: date.format("%Y-%m-%d %H:%M").to_string(),
+        score: game.total_score(),
+        edited: game.editing(),
+    }
+}
+
+/// What's to be archived of a game, taken while the game is locked so that
+/// it can be written out after the lock is let go. Games that never got
+/// going aren't worth keeping.
+pub fn snapshot(game: &GameState) -> Option<ArchivedGame> {
+    if !game.started() {
+        return None;
+    }
+    Some(ArchivedGame {
+        meta: meta(game),
+        game: serde_json::to_value(&derbylive::game_doc(game)).unwrap(),
+    })
+}
+
+/// Save a game to the archive, replacing any earlier copy of it. It's
+/// written to a temporary file that then takes the old copy's place, so
+/// that a crash part way through leaves the old copy as it was.
+pub fn save(archived: &ArchivedGame) -> io::Result<()> {
+    let dir = archive_dir();
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{}.json", archived.meta.id));
+    let tmp = dir.join(format!("{}.json.tmp", archived.meta.id));
+    {
+        let file = File::create(&tmp)?;
+        serde_json::to_writer(&file, archived)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp, &path)
+}
+
+/// Save a game, if there's anything to save, logging rather than failing
+/// if that doesn't work.
+pub fn save_or_log(archived: Option<ArchivedGame>) {
+    if let Some(ref archived) = archived {
+        if let Err(e) = save(archived) {
+            println!("Can't archive the game: {}", e);
+        }
+    }
+}
+
+fn load(id: &str) -> ApiResult<ArchivedGame> {
+    if !valid_id(id) {
+        return Err(ApiError::not_found("no_such_game", format!("No archived game {:?}", id)));
+    }
+    let file = File::open(archive_dir().join(format!("{}.json", id))).map_err(|e| {
+        if e.kind() == io::ErrorKind::NotFound {
+            ApiError::not_found("no_such_game", format!("No archived game {:?}", id))
+        } else {
+            ApiError::from(e)
+        }
+    })?;
+    serde_json::from_reader(file).map_err(|e| {
+        ApiError::from(io::Error::new(io::ErrorKind::InvalidData, e))
+    })
+}
+
+fn list() -> Vec<ArchiveMeta> {
+    let entries = match fs::read_dir(archive_dir()) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let mut games = entries.filter_map(|e| e.ok()).filter_map(|entry| {
+        let path = entry.path();
+        let id = match path.file_stem().and_then(|s| s.to_str()) {
+            Some(id) if valid_id(id) => String::from(id),
+            _ => return None,
+        };
+        load(&id).ok().map(|g| g.meta)
+    }).collect::<Vec<_>>();
+    games.sort_by(|a, b| b.id.cmp(&a.id));
+    games
+}
+
+/// Archive the game as soon as it's over.
+pub fn spawn_archiver() {
+    let rx = events::subscribe();
+    thread::spawn(move || {
+        for notice in rx {
+            if let Event::GameOver = notice.event {
+                let archived = guard::get_game().as_ref().and_then(snapshot);
+                save_or_log(archived);
+            }
+        }
+    });
+}
+
+#[derive(Serialize)]
+struct TeamJamRow {
+    jammer: String,
+    trips: Vec<u8>,
+    points: u32,
+    lead: bool,
+    lost: bool,
+    call: bool,
+    star_pass: bool,
+}
+
+#[derive(Serialize)]
+struct JamRow {
+    period: u8,
+    jam: u8,
+    home: TeamJamRow,
+    away: TeamJamRow,
+}
+
+#[derive(Serialize)]
+struct SkaterRow {
+    number: String,
+    name: String,
+    /// Each penalty as its code and where it was called, like `X 1/5`.
+    penalties: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct TeamView {
+    name: String,
+    score: u32,
+    skaters: Vec<SkaterRow>,
+}
+
+#[derive(Serialize)]
+struct GameView {
+    meta: ArchiveMeta,
+    teams: Vec<TeamView>,
+    jams: Vec<JamRow>,
+}
+
+fn team_jam_row(game: &GameState, jamidx: usize, team: Team) -> TeamJamRow {
+    let tj = &game.jams()[jamidx][team];
+    let skaters = &game.roster(team).skaters;
+    TeamJamRow {
+        jammer: tj.jammer().and_then(|idx| skaters.get(idx))
+            .map_or(String::new(), |s| s.number.clone()),
+        trips: tj.trips(), points: tj.points(),
+        lead: tj.lead(), lost: tj.lost(), call: tj.call(), star_pass: tj.starpass(),
+    }
+}
+
+fn team_view(game: &GameState, team: Team) -> TeamView {
+    let mut penalties = game.team_penalties(team);
+    let roster = game.roster(team);
+    TeamView {
+        name: roster.name.clone(),
+        score: if team == Team::Home { game.total_score().0 } else { game.total_score().1 },
+        skaters: roster.skaters.iter().map(|s| SkaterRow {
+            number: s.number.clone(), name: s.name.clone(),
+            penalties: penalties.remove(&s.number).unwrap_or_default().iter().map(|p| {
+                format!("{} {}/{}", p.code(), p.jam().0, p.jam().1)
+            }).collect(),
+        }).collect(),
+    }
+}
+
+fn game_view(meta: ArchiveMeta, game: &GameState) -> GameView {
+    let jams = (0..game.jams().len()).filter(|&i| game.jams()[i].played())
+        .map(|i| {
+            let (period, jam) = game.jamidx_to_periodjam(i);
+            JamRow { period: period, jam: jam,
+                     home: team_jam_row(game, i, Team::Home),
+                     away: team_jam_row(game, i, Team::Away) }
+        }).collect();
+    GameView {
+        meta: meta, jams: jams,
+        teams: vec![team_view(game, Team::Home), team_view(game, Team::Away)],
+    }
+}
+
+#[get("/archive")]
+fn archive_page() -> Result<content::Html<String>, handlebars::RenderError> {
+    staticpages::render("archive", &list())
+}
+
+#[get("/archive.json")]
+fn archive_list() -> Json<Vec<ArchiveMeta>> {
+    Json(list())
+}
+
+#[get("/archive/<id>")]
+fn archived_game(id: String) -> ApiResult<content::Html<String>> {
+    let archived = load(&id)?;
+    let game = import::from_value(archived.game)?;
+    staticpages::render("archivedgame", &game_view(archived.meta, &game)).map_err(|e| {
+        ApiError::new(Status::InternalServerError, "render_error", format!("{}", e))
+    })
+}
+
+/// The paper reports for an archived game.
+#[get("/archive/<id>/report")]
+fn archived_report(id: String) -> ApiResult<content::Html<String>> {
+    let game = import::from_value(load(&id)?.game)?;
+    reports::render(&game).map_err(|e| {
+        ApiError::new(Status::InternalServerError, "render_error", format!("{}", e))
+    })
+}
+
+/// The game's DerbyJSON document, for taking elsewhere or importing to
+/// edit.
+#[get("/archive/<id>/derbyjson")]
+fn export_game(id: String) -> ApiResult<Json<Value>> {
+    Ok(Json(load(&id)?.game))
+}
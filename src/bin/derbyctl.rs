@@ -0,0 +1,241 @@
+/*! derbyctl: drive a derbyboard game from the terminal.
+
+    This talks to a running derbyboard server over the same HTTP API the
+    jam timer and penalty pages use, so it's handy for rehearsals and as a
+    backup when the JT tablet dies.
+ */
+
+extern crate hyper;
+extern crate serde_json;
+
+use std::env;
+use std::io::{self, BufRead, Read, Write};
+use std::process;
+use std::thread;
+use std::time::Duration;
+
+use hyper::header::ContentType;
+use serde_json::Value;
+
+const USAGE: &'static str = "\
+Usage: derbyctl [--server URL] COMMAND [ARGS]
+       derbyctl [--server URL] shell
+
+Commands:
+    start                       start a jam
+    stop                        stop the jam
+    timeout [home|away]         official timeout, or a team timeout
+    endtimeout                  end the timeout or review
+    review home|away            official review
+    retained home|away          the review was retained
+    lost home|away              the review was lost
+    starpass home|away          star pass in the current jam
+    score HOME AWAY             adjust the current jam score, e.g. score 4 0
+    penalty home|away NUM CODE  add a penalty, e.g. penalty home 12 B
+    status                      print the scoreboard once
+    watch                       keep printing the scoreboard
+
+The server defaults to http://localhost:8000. `shell` reads commands from
+standard input, one per line.
+";
+
+struct Server {
+    url: String,
+    client: hyper::Client,
+}
+
+impl Server {
+    fn get(&self, path: &str) -> Result<Value, String> {
+        let res = self.client.get(&format!("{}{}", self.url, path)).send();
+        Server::response(res)
+    }
+
+    fn post(&self, path: &str, body: Value) -> Result<Value, String> {
+        let body = body.to_string();
+        let res = self.client.post(&format!("{}{}", self.url, path))
+            .header(ContentType::json())
+            .body(body.as_str())
+            .send();
+        Server::response(res)
+    }
+
+    fn response(res: hyper::Result<hyper::client::Response>) -> Result<Value, String> {
+        let mut res = res.map_err(|e| format!("can't reach server: {}", e))?;
+        let mut body = String::new();
+        res.read_to_string(&mut body).map_err(|e| format!("{}", e))?;
+        if !res.status.is_success() {
+            // API errors come back as {"error": CODE, "message": MESSAGE}.
+            let message = serde_json::from_str::<Value>(&body).ok()
+                .and_then(|v| v["message"].as_str().map(String::from))
+                .unwrap_or(body);
+            return Err(format!("server said {}: {}", res.status, message));
+        }
+        // Some commands just answer "success".
+        Ok(serde_json::from_str(&body).unwrap_or(Value::String(body)))
+    }
+}
+
+fn team(arg: Option<&String>) -> Result<&'static str, String> {
+    match arg.map(|s| s.as_str()) {
+        Some("home") | Some("1") => Ok("Home"),
+        Some("away") | Some("2") => Ok("Away"),
+        Some(other) => Err(format!("expected home or away, not {:?}", other)),
+        None => Err(String::from("expected home or away")),
+    }
+}
+
+fn team_number(name: &str) -> u8 {
+    if name == "Home" { 1 } else { 2 }
+}
+
+fn int(arg: Option<&String>) -> Result<i8, String> {
+    match arg {
+        Some(s) => s.parse().map_err(|_| format!("expected a number, not {:?}", s)),
+        None => Err(String::from("expected a number")),
+    }
+}
+
+fn format_time(dur: &Value) -> String {
+    let secs = dur["secs"].as_u64().unwrap_or(0);
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+fn clock_label(clock: &Value) -> String {
+    let obj = match clock.as_object() {
+        Some(o) => o,
+        None => return String::from("-"),
+    };
+    let (kind, val) = match obj.iter().next() {
+        Some(kv) => kv,
+        None => return String::from("-"),
+    };
+    match kind.as_str() {
+        "jam" => format!("Jam {} {}", val[0], format_time(&val[1])),
+        "lineup" => format!("Lineup {}", format_time(val)),
+        "timeout" => format!("Official Timeout {}", format_time(val)),
+        "team_timeout" => format!("{} Timeout {}", val[0].as_str().unwrap_or(""),
+                                  format_time(&val[1])),
+        "review" => format!("{} Review {}", val[0].as_str().unwrap_or(""),
+                            format_time(&val[1])),
+        "intermission" => format!("Halftime {}", format_time(val)),
+        "time_to_derby" => format!("Time To Derby {}", format_time(val)),
+        _ => String::from("-"),
+    }
+}
+
+fn render(update: &Value) -> String {
+    format!("P{} {} | {} | Home {} ({:+}) TO {} OR {} | Away {} ({:+}) TO {} OR {}",
+            update["gameclock"][0], format_time(&update["gameclock"][1]),
+            clock_label(&update["activeclock"]),
+            update["score"][0], update["jamscore"][0].as_i64().unwrap_or(0),
+            update["timeouts"][0], update["reviews"][0],
+            update["score"][1], update["jamscore"][1].as_i64().unwrap_or(0),
+            update["timeouts"][1], update["reviews"][1])
+}
+
+fn watch(server: &Server) -> Result<(), String> {
+    let stdout = io::stdout();
+    loop {
+        let line = render(&server.get("/score/update")?);
+        // Pad so a shorter line fully overwrites a longer one.
+        print!("\r{:<100}", line);
+        stdout.lock().flush().map_err(|e| format!("{}", e))?;
+        thread::sleep(Duration::from_millis(500));
+    }
+}
+
+fn score_command(server: &Server, cmd: Value) -> Result<(), String> {
+    server.post("/score/update", cmd)?;
+    Ok(())
+}
+
+fn run(server: &Server, args: &[String]) -> Result<(), String> {
+    let cmd = match args.first() {
+        Some(c) => c.as_str(),
+        None => return Err(String::from("no command given")),
+    };
+    let arg = |i: usize| args.get(i);
+    match cmd {
+        "start" => score_command(server, json_cmd("start_jam", Value::Null)),
+        "stop" => score_command(server, json_cmd("stop_jam", Value::Null)),
+        "timeout" => match arg(1) {
+            None => score_command(server, json_cmd("official_timeout", Value::Null)),
+            t => score_command(server, json_cmd("team_timeout", team(t)?.into())),
+        },
+        "endtimeout" => score_command(server, json_cmd("end_timeout", Value::Null)),
+        "review" => score_command(server, json_cmd("official_review", team(arg(1))?.into())),
+        "retained" => score_command(server, json_cmd("review_retained", team(arg(1))?.into())),
+        "lost" => score_command(server, json_cmd("review_lost", team(arg(1))?.into())),
+        "starpass" => score_command(server, json_cmd("star_pass", team(arg(1))?.into())),
+        "score" => {
+            let adj = vec![Value::from(int(arg(1))?), Value::from(int(arg(2))?)];
+            score_command(server, json_cmd("score_adj", Value::Array(adj)))
+        },
+        "penalty" => {
+            let t = team_number(team(arg(1))?);
+            let (skater, code) = match (arg(2), arg(3)) {
+                (Some(s), Some(c)) if c.chars().count() == 1 => (s, c),
+                _ => return Err(String::from("expected a skater number and a penalty code")),
+            };
+            let mut body = serde_json::Map::new();
+            body.insert(String::from("skater"), Value::from(skater.as_str()));
+            body.insert(String::from("code"), Value::from(code.to_uppercase()));
+            server.post(&format!("/penalties/{}", t), Value::Object(body))?;
+            Ok(())
+        },
+        "status" => {
+            println!("{}", render(&server.get("/score/update")?));
+            Ok(())
+        },
+        "watch" => watch(server),
+        "shell" => shell(server),
+        "help" => { print!("{}", USAGE); Ok(()) },
+        other => Err(format!("unknown command {:?}", other)),
+    }
+}
+
+fn json_cmd(name: &str, arg: Value) -> Value {
+    let mut obj = serde_json::Map::new();
+    obj.insert(String::from(name), arg);
+    Value::Object(obj)
+}
+
+fn shell(server: &Server) -> Result<(), String> {
+    let stdin = io::stdin();
+    print!("> ");
+    io::stdout().flush().ok();
+    for line in stdin.lock().lines() {
+        let line = line.map_err(|e| format!("{}", e))?;
+        let words = line.split_whitespace().map(String::from).collect::<Vec<_>>();
+        if !words.is_empty() {
+            if let Err(e) = run(server, &words) {
+                println!("error: {}", e);
+            }
+        }
+        print!("> ");
+        io::stdout().flush().ok();
+    }
+    println!("");
+    Ok(())
+}
+
+fn main() {
+    let mut args = env::args().skip(1).collect::<Vec<_>>();
+    let mut url = String::from("http://localhost:8000");
+    if args.len() >= 2 && args[0] == "--server" {
+        url = args[1].trim_right_matches('/').to_string();
+        args.drain(..2);
+    }
+    if args.is_empty() || args[0] == "--help" || args[0] == "-h" {
+        print!("{}", USAGE);
+        return;
+    }
+
+    let mut client = hyper::Client::new();
+    client.set_read_timeout(Some(Duration::new(5, 0)));
+    let server = Server { url: url, client: client };
+    if let Err(e) = run(&server, &args) {
+        eprintln!("derbyctl: {}", e);
+        process::exit(1);
+    }
+}
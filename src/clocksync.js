@@ -0,0 +1,51 @@
+"use strict";
+
+/* Keeps a display's clocks running smoothly between updates, and in step
+   with every other display. Each update carries the server's time and the
+   clocks as of that moment, so we estimate the offset between our clock
+   and the server's (trusting the sample with the quickest round trip the
+   most) and run the clocks forward from there. */
+var ClockSync = {
+    offset: 0,
+    bestRtt: Infinity,
+    last: null,
+
+    /* Call with each update, and the local times the request was sent and
+       the response was received. */
+    observe: function (data, sent, received) {
+        var rtt = received - sent;
+        // Let old estimates go stale slowly, so we follow drift.
+        this.bestRtt += 1;
+        if (rtt <= this.bestRtt) {
+            this.bestRtt = rtt;
+            this.offset = data.server_time - (sent + received) / 2;
+        }
+        this.last = data;
+    },
+
+    /* Our best guess at the server's clock right now. */
+    now: function () {
+        return Math.round(Date.now() + this.offset);
+    },
+
+    elapsed: function () {
+        return Math.max(0, this.now() - this.last.server_time);
+    },
+
+    /* The period clock, in milliseconds. */
+    gameclock: function () {
+        var clocks = this.last.clocks;
+        if (!clocks.game_running) return clocks.game_ms;
+        return Math.max(0, clocks.game_ms - this.elapsed());
+    },
+
+    /* The active clock (jam, lineup, timeout...), in milliseconds. */
+    activeclock: function () {
+        var clocks = this.last.clocks;
+        if (!clocks.active_running) return clocks.active_ms;
+        if (clocks.active_counts_down) {
+            return Math.max(0, clocks.active_ms - this.elapsed());
+        }
+        return clocks.active_ms + this.elapsed();
+    },
+};
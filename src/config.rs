@@ -0,0 +1,228 @@
+/*! Server configuration. Settings come from an optional JSON config file
+    given with `--config`, and can be overridden by command-line flags.
+    Everything is checked at startup so that a bad setting is reported
+    clearly instead of causing a failure halfway through a game.
+ */
+
+use std::env;
+use std::fmt;
+use std::fs::{self, File};
+use std::path::PathBuf;
+use std::sync::{RwLock, RwLockReadGuard};
+
+use serde_json;
+
+use gamestate::ruleset::{Ruleset, RULESET_NAMES};
+
+pub struct Config {
+    pub address: String,
+    pub port: u16,
+    pub roster_dir: PathBuf,
+    /// Where games, uploaded media and other saved state live.
+    pub data_dir: PathBuf,
+    pub ruleset: Ruleset,
+    pub templates_dir: Option<PathBuf>,
+    /// Where to serve the CRG scoreboard WebSocket, if anywhere.
+    pub crg_port: Option<u16>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            address: String::from("localhost"),
+            port: 8000,
+            roster_dir: PathBuf::from("rosters"),
+            data_dir: PathBuf::from("data"),
+            ruleset: Ruleset::default(),
+            templates_dir: None,
+            crg_port: None,
+        }
+    }
+}
+
+/// The config file format. Everything is optional.
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    address: Option<String>,
+    port: Option<u16>,
+    roster_dir: Option<PathBuf>,
+    data_dir: Option<PathBuf>,
+    ruleset: Option<String>,
+    templates_dir: Option<PathBuf>,
+    crg_port: Option<u16>,
+}
+
+pub struct ConfigError(String);
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+fn err<T, S: Into<String>>(s: S) -> Result<T, ConfigError> {
+    Err(ConfigError(s.into()))
+}
+
+pub const USAGE: &'static str = "\
+Usage: derbyboard [options]
+
+Options:
+    --config FILE       read settings from a JSON config file
+    --address ADDR      address to listen on (default localhost)
+    --port PORT         port to listen on (default 8000)
+    --roster-dir DIR    directory of team rosters (default rosters)
+    --data-dir DIR      directory for saved games and media (default data)
+    --ruleset NAME      default ruleset: wftda or short (default wftda)
+    --templates DIR     directory of page templates overriding the built-in ones
+    --crg-port PORT     serve the CRG scoreboard WebSocket on this port
+    --help              show this message
+";
+
+fn load_file(path: &PathBuf) -> Result<ConfigFile, ConfigError> {
+    let file = match File::open(path) {
+        Ok(f) => f,
+        Err(e) => return err(format!("can't open config file {}: {}", path.display(), e)),
+    };
+    serde_json::from_reader(file).or_else(|e| {
+        err(format!("bad config file {}: {}", path.display(), e))
+    })
+}
+
+fn dir_exists(what: &str, dir: &PathBuf) -> Result<(), ConfigError> {
+    if dir.is_dir() {
+        Ok(())
+    } else {
+        err(format!("{} {} is not a directory", what, dir.display()))
+    }
+}
+
+/// Build the configuration from the command line. Returns `Ok(None)` if
+/// the user just asked for help.
+pub fn from_args<I>(args: I) -> Result<Option<Config>, ConfigError>
+    where I: Iterator<Item=String>
+{
+    let mut flags = ConfigFile::default();
+    let mut config_path = None;
+    let mut args = args.skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--help" || arg == "-h" {
+            return Ok(None);
+        }
+        let value = match args.next() {
+            Some(v) => v,
+            None => return err(format!("{} needs a value", arg)),
+        };
+        match arg.as_str() {
+            "--config" => config_path = Some(PathBuf::from(value)),
+            "--address" => flags.address = Some(value),
+            "--port" => match value.parse() {
+                Ok(p) => flags.port = Some(p),
+                Err(_) => return err(format!("bad port {:?}", value)),
+            },
+            "--roster-dir" => flags.roster_dir = Some(PathBuf::from(value)),
+            "--data-dir" => flags.data_dir = Some(PathBuf::from(value)),
+            "--ruleset" => flags.ruleset = Some(value),
+            "--templates" => flags.templates_dir = Some(PathBuf::from(value)),
+            "--crg-port" => match value.parse() {
+                Ok(p) => flags.crg_port = Some(p),
+                Err(_) => return err(format!("bad CRG port {:?}", value)),
+            },
+            _ => return err(format!("unknown option {}", arg)),
+        }
+    }
+
+    let file = match config_path {
+        Some(ref path) => load_file(path)?,
+        None => ConfigFile::default(),
+    };
+    let mut config = Config::default();
+    if let Some(a) = flags.address.or(file.address) { config.address = a }
+    if let Some(p) = flags.port.or(file.port) { config.port = p }
+    if let Some(d) = flags.roster_dir.or(file.roster_dir) { config.roster_dir = d }
+    if let Some(d) = flags.data_dir.or(file.data_dir) { config.data_dir = d }
+    if let Some(name) = flags.ruleset.or(file.ruleset) {
+        config.ruleset = match Ruleset::by_name(&name) {
+            Some(r) => r,
+            None => return err(format!("unknown ruleset {:?}, expected one of {}",
+                                       name, RULESET_NAMES.join(", "))),
+        };
+    }
+    config.templates_dir = flags.templates_dir.or(file.templates_dir);
+    config.crg_port = flags.crg_port.or(file.crg_port);
+
+    if config.address.is_empty() {
+        return err("address can't be empty");
+    }
+    if config.port == 0 {
+        return err("port can't be 0");
+    }
+    if config.crg_port == Some(0) || config.crg_port == Some(config.port) {
+        return err("the CRG port must be non-zero and differ from the main port");
+    }
+    if !config.roster_dir.is_dir() {
+        println!("Warning: roster directory {} is not a directory; starting with no rosters",
+                 config.roster_dir.display());
+    }
+    if let Some(ref dir) = config.templates_dir {
+        dir_exists("templates directory", dir)?;
+    }
+    if let Err(e) = fs::create_dir_all(&config.data_dir) {
+        return err(format!("can't create data directory {}: {}",
+                           config.data_dir.display(), e));
+    }
+    Ok(Some(config))
+}
+
+/// Parse the process's own command line.
+pub fn from_env() -> Result<Option<Config>, ConfigError> {
+    from_args(env::args())
+}
+
+pub fn set(config: Config) {
+    *CONFIG.write().unwrap() = config;
+}
+
+pub fn get<'a>() -> RwLockReadGuard<'a, Config> {
+    CONFIG.read().unwrap()
+}
+
+lazy_static! {
+    static ref CONFIG: RwLock<Config> = RwLock::new(Config::default());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use super::from_args;
+
+    fn args(a: &[&str]) -> Vec<String> {
+        let tmp = env::temp_dir().to_string_lossy().into_owned();
+        let mut v = vec![String::from("derbyboard"), String::from("--roster-dir"),
+                         String::from("."), String::from("--data-dir"), tmp];
+        v.extend(a.iter().map(|s| String::from(*s)));
+        v
+    }
+
+    #[test]
+    fn test_flags() {
+        let config = from_args(args(&["--port", "9000", "--ruleset", "short"]).into_iter());
+        let config = config.ok().unwrap().unwrap();
+        assert_eq!(config.port, 9000);
+        assert_eq!(config.ruleset.period_length.as_secs(), 15 * 60);
+    }
+
+    #[test]
+    fn test_bad_flags() {
+        assert!(from_args(args(&["--port", "http"]).into_iter()).is_err());
+        assert!(from_args(args(&["--ruleset", "nope"]).into_iter()).is_err());
+        assert!(from_args(args(&["--port"]).into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_missing_roster_dir() {
+        let config = from_args(args(&["--roster-dir", "/nonexistent"]).into_iter());
+        assert!(config.ok().unwrap().is_some());
+    }
+}
@@ -0,0 +1,317 @@
+/*! A compatibility layer for displays and overlays written for CRG
+    Scoreboard. It speaks CRG's WebSocket protocol on its own port (set
+    with `--crg-port`, as Rocket can't do WebSockets): clients send
+    `{"action": "Register", "paths": ["ScoreBoard.Clock(Jam)", ...]}` and
+    get back `{"state": {"ScoreBoard.Clock(Jam).Time": 87000, ...}}`, first
+    for everything under the paths they asked for and then for each value
+    as it changes. The key space follows CRG 4's `ScoreBoard` tree, for the
+    parts derbyboard has: clocks, scores, teams, jammers and timeouts.
+    It's read-only; commands from clients are ignored.
+ */
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use serde_json::{self, Value};
+use ws;
+
+use config;
+use gamestate::{ActiveClock, GameState};
+use gamestate::jamstate::Team;
+use guard;
+
+/// How often to look for changes to send out.
+const UPDATE_INTERVAL_MS: u32 = 100;
+
+type State = BTreeMap<String, Value>;
+
+struct Client {
+    out: ws::Sender,
+    paths: Vec<String>,
+}
+
+struct Hub {
+    /// The state as last sent out.
+    state: State,
+    clients: Vec<Client>,
+}
+
+#[derive(Deserialize)]
+struct ClientMessage {
+    action: String,
+    #[serde(default)]
+    paths: Vec<String>,
+}
+
+fn team_id(team: Team) -> &'static str {
+    match team {
+        Team::Home => "1",
+        Team::Away => "2",
+    }
+}
+
+/// Whether a key is under a registered path. `(*)` in a path matches any
+/// id, as in `ScoreBoard.Team(*).Score`.
+fn matches(path: &str, key: &str) -> bool {
+    let (mut path, mut key) = (path, key);
+    loop {
+        if path.is_empty() {
+            return key.is_empty() || key.starts_with('.') || key.starts_with('(');
+        }
+        if path.starts_with("(*)") {
+            if !key.starts_with('(') {
+                return false;
+            }
+            match key.find(')') {
+                Some(end) => key = &key[end + 1..],
+                None => return false,
+            }
+            path = &path[3..];
+            continue;
+        }
+        let ch = path.chars().next().unwrap();
+        if !key.starts_with(ch) {
+            return false;
+        }
+        path = &path[ch.len_utf8()..];
+        key = &key[ch.len_utf8()..];
+    }
+}
+
+fn filter(state: &State, paths: &[String]) -> State {
+    state.iter().filter(|&(k, _)| paths.iter().any(|p| matches(p, k)))
+        .map(|(k, v)| (k.clone(), v.clone())).collect()
+}
+
+fn send(out: &ws::Sender, state: State) {
+    if state.is_empty() {
+        return;
+    }
+    let mut msg = BTreeMap::new();
+    msg.insert("state", state);
+    let _ = out.send(serde_json::to_string(&msg).unwrap());
+}
+
+fn clock(state: &mut State, name: &str, number: u8, ms: u64, running: bool,
+         counts_down: bool) {
+    let prefix = format!("ScoreBoard.Clock({})", name);
+    state.insert(format!("{}.Name", prefix), Value::from(name));
+    state.insert(format!("{}.Number", prefix), Value::from(number));
+    state.insert(format!("{}.Time", prefix), Value::from(ms));
+    state.insert(format!("{}.Running", prefix), Value::from(running));
+    state.insert(format!("{}.Direction", prefix), Value::from(counts_down));
+}
+
+/// Where a roster's logo can be fetched: as it is if it's already a URL,
+/// or under `/logos/` if it's a file in the roster directory.
+fn logo_url(logo: &str) -> String {
+    if logo.starts_with("http:") || logo.starts_with("https:") {
+        return String::from(logo);
+    }
+    let mut url = String::from("/logos");
+    for segment in logo.split('/') {
+        url.push('/');
+        for &b in segment.as_bytes() {
+            match b {
+                b'A'...b'Z' | b'a'...b'z' | b'0'...b'9' | b'-' | b'_' | b'.' | b'~' =>
+                    url.push(b as char),
+                _ => url.push_str(&format!("%{:02X}", b)),
+            }
+        }
+    }
+    url
+}
+
+fn team(state: &mut State, game: &GameState, team: Team) {
+    let prefix = format!("ScoreBoard.Team({})", team_id(team));
+    let pick = |pair: (u32, u32)| if team == Team::Home { pair.0 } else { pair.1 };
+    let pick8 = |pair: (u8, u8)| if team == Team::Home { pair.0 } else { pair.1 };
+    let roster = game.roster(team);
+    // Between jams, show the jam that just finished.
+    let jam = match game.prev_jam() {
+        Some(prev) if !game.cur_jam().played() => prev,
+        _ => game.cur_jam(),
+    };
+    let tj = &jam[team];
+    let (in_timeout, in_review) = match game.get_active_clock() {
+        ActiveClock::team_timeout(t, _) => (t == team, false),
+        ActiveClock::review(t, _) => (false, t == team),
+        _ => (false, false),
+    };
+    state.insert(format!("{}.Name", prefix), Value::from(roster.name.clone()));
+    state.insert(format!("{}.Logo", prefix),
+                 Value::from(roster.logo.as_ref().map_or(String::new(), |l| logo_url(l))));
+    state.insert(format!("{}.Score", prefix), Value::from(pick(game.total_score())));
+    state.insert(format!("{}.JamScore", prefix), Value::from(pick(jam.jam_score())));
+    state.insert(format!("{}.Timeouts", prefix), Value::from(pick8(game.timeouts())));
+    state.insert(format!("{}.OfficialReviews", prefix), Value::from(pick8(game.reviews())));
+    state.insert(format!("{}.InTimeout", prefix), Value::from(in_timeout));
+    state.insert(format!("{}.InOfficialReview", prefix), Value::from(in_review));
+    state.insert(format!("{}.Lead", prefix), Value::from(tj.lead()));
+    state.insert(format!("{}.Lost", prefix), Value::from(tj.lost()));
+    state.insert(format!("{}.Calloff", prefix), Value::from(tj.call()));
+    state.insert(format!("{}.StarPass", prefix), Value::from(tj.starpass()));
+    for &(position, skater) in [("Jammer", tj.jammer()), ("Pivot", tj.pivot())].iter() {
+        if let Some(s) = skater.and_then(|idx| roster.skaters.get(idx)) {
+            let pos = format!("{}.Position({})", prefix, position);
+            state.insert(format!("{}.Name", pos), Value::from(s.name.clone()));
+            state.insert(format!("{}.RosterNumber", pos), Value::from(s.number.clone()));
+        }
+    }
+}
+
+fn game_state(game: &GameState) -> State {
+    let mut state = State::new();
+    let clocks = game.clock_state();
+    let (period, jam) = (game.get_time().0, game.jamnum());
+    let active = game.get_active_clock();
+    let (name, owner, review) = match active {
+        ActiveClock::jam(..) => ("Jam", "", false),
+        ActiveClock::lineup(_) => ("Lineup", "", false),
+        ActiveClock::timeout(_) => ("Timeout", "O", false),
+        ActiveClock::team_timeout(t, _) => ("Timeout", team_id(t), false),
+        ActiveClock::review(t, _) => ("Timeout", team_id(t), true),
+        ActiveClock::intermission(_) | ActiveClock::time_to_derby(_) => ("Intermission", "", false),
+        ActiveClock::none => ("", "", false),
+    };
+    clock(&mut state, "Period", period, clocks.game_ms, clocks.game_running, true);
+    for &n in ["Jam", "Lineup", "Timeout", "Intermission"].iter() {
+        let number = if n == "Jam" { jam } else { 0 };
+        if n == name {
+            clock(&mut state, n, number, clocks.active_ms, clocks.active_running,
+                  clocks.active_counts_down);
+        } else {
+            clock(&mut state, n, number, 0, false, n != "Lineup");
+        }
+    }
+    state.insert(String::from("ScoreBoard.InPeriod"), Value::from(clocks.game_running));
+    state.insert(String::from("ScoreBoard.InJam"), Value::from(name == "Jam"));
+    state.insert(String::from("ScoreBoard.TimeoutOwner"), Value::from(owner));
+    state.insert(String::from("ScoreBoard.OfficialReview"), Value::from(review));
+    team(&mut state, game, Team::Home);
+    team(&mut state, game, Team::Away);
+    state
+}
+
+fn current_state() -> State {
+    match *guard::get_game() {
+        Some(ref game) => game_state(game),
+        None => State::new(),
+    }
+}
+
+/// Work out what changed since last time, and send it to whoever asked
+/// for it. Keys that have gone away are sent as null, as CRG does.
+fn broadcast() {
+    let new = current_state();
+    let mut hub = HUB.lock().unwrap();
+    let mut changed = State::new();
+    for (k, v) in &new {
+        if hub.state.get(k) != Some(v) {
+            changed.insert(k.clone(), v.clone());
+        }
+    }
+    for k in hub.state.keys() {
+        if !new.contains_key(k) {
+            changed.insert(k.clone(), Value::Null);
+        }
+    }
+    hub.state = new;
+    if changed.is_empty() {
+        return;
+    }
+    for client in &hub.clients {
+        send(&client.out, filter(&changed, &client.paths));
+    }
+}
+
+struct Connection {
+    out: ws::Sender,
+}
+
+impl ws::Handler for Connection {
+    fn on_open(&mut self, _: ws::Handshake) -> ws::Result<()> {
+        HUB.lock().unwrap().clients.push(Client { out: self.out.clone(), paths: Vec::new() });
+        Ok(())
+    }
+
+    fn on_message(&mut self, msg: ws::Message) -> ws::Result<()> {
+        let parsed = msg.as_text().ok().and_then(|t| serde_json::from_str(t).ok());
+        let msg: ClientMessage = match parsed {
+            Some(m) => m,
+            None => return Ok(()),
+        };
+        match msg.action.as_str() {
+            "Register" => {
+                let mut hub = HUB.lock().unwrap();
+                let initial = filter(&hub.state, &msg.paths);
+                let id = self.out.connection_id();
+                let client = hub.clients.iter_mut().find(|c| c.out.connection_id() == id);
+                if let Some(client) = client {
+                    client.paths.extend(msg.paths);
+                }
+                send(&self.out, initial);
+            },
+            "Ping" => { self.out.send(r#"{"Pong": ""}"#)?; },
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn on_close(&mut self, _: ws::CloseCode, _: &str) {
+        let id = self.out.connection_id();
+        HUB.lock().unwrap().clients.retain(|c| c.out.connection_id() != id);
+    }
+}
+
+/// Start the CRG WebSocket server, if there's a port configured for it.
+/// The port is bound before this returns, so that a port that's already
+/// taken stops the server from starting rather than going unnoticed.
+pub fn spawn_server() -> Result<(), String> {
+    let port = match config::get().crg_port {
+        Some(p) => p,
+        None => return Ok(()),
+    };
+    let addr = (config::get().address.clone(), port);
+    let server = ws::WebSocket::new(|out| Connection { out: out })
+        .and_then(|ws| ws.bind(addr))
+        .map_err(|e| format!("can't serve the CRG WebSocket on port {}: {}", port, e))?;
+    thread::spawn(move || {
+        if let Err(e) = server.run() {
+            println!("CRG WebSocket server stopped: {}", e);
+        }
+    });
+    thread::spawn(|| {
+        loop {
+            thread::sleep(Duration::new(0, UPDATE_INTERVAL_MS * 1_000_000));
+            broadcast();
+        }
+    });
+    Ok(())
+}
+
+lazy_static! {
+    static ref HUB: Mutex<Hub> = Mutex::new(Hub { state: State::new(), clients: Vec::new() });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{logo_url, matches};
+
+    #[test]
+    fn test_matches() {
+        assert!(matches("ScoreBoard.Clock(Jam)", "ScoreBoard.Clock(Jam).Time"));
+        assert!(matches("ScoreBoard.Team(*).Score", "ScoreBoard.Team(2).Score"));
+        assert!(matches("ScoreBoard", "ScoreBoard.InJam"));
+        assert!(!matches("ScoreBoard.Team(1)", "ScoreBoard.Team(2).Score"));
+        assert!(!matches("ScoreBoard.InJam", "ScoreBoard.InJamX"));
+    }
+
+    #[test]
+    fn test_logo_url() {
+        assert_eq!(logo_url("teams/Roller Girls.png"), "/logos/teams/Roller%20Girls.png");
+        assert_eq!(logo_url("https://example.com/a.png"), "https://example.com/a.png");
+    }
+}
@@ -0,0 +1,309 @@
+/*! The game as a live DerbyJSON document, for frontends that would rather
+    speak a standard format than our own API. `/derbyjson/game` gives the
+    whole game so far, with the sequence number of the next update in
+    `next`. `/derbyjson/updates?since=N` then long-polls for updates from
+    the event bus, each of which carries a piece of the document to put in
+    place: a whole jam, keyed by period and jam number, or a timeout,
+    keyed by its place in the list of timeouts. A `reset` update means the
+    client should fetch the whole game again, either because it fell too
+    far behind or because a correction has renumbered things.
+ */
+
+use std::collections::BTreeMap;
+
+use derbyjson;
+use rocket_contrib::Json;
+
+use events::{self, Event, Notice, TimeoutKind};
+use gamestate::{GameState, TimeoutEntry};
+use gamestate::igrf::Igrf;
+use gamestate::jamstate::{JamState, Team};
+use guard::{self, Game};
+use officials::Official;
+
+const DERBYJSON_VERSION: &'static str = "v0.2";
+
+fn team_key(team: Team) -> &'static str {
+    match team {
+        Team::Home => "home",
+        Team::Away => "away",
+    }
+}
+
+#[derive(Serialize)]
+struct LineupDoc {
+    skater: String,
+    position: &'static str,
+}
+
+#[derive(Serialize)]
+struct PassDoc {
+    number: u8,
+    score: u8,
+    /// Scored by the pivot, after a star pass.
+    pivot: bool,
+}
+
+#[derive(Serialize)]
+struct PenaltyDoc {
+    skater: String,
+    penalty: char,
+}
+
+#[derive(Serialize)]
+struct TeamJamDoc {
+    lineup: Vec<LineupDoc>,
+    passes: Vec<PassDoc>,
+    penalties: Vec<PenaltyDoc>,
+    lead: bool,
+    lost: bool,
+    call: bool,
+    #[serde(rename = "starPass")]
+    star_pass: bool,
+    /// Points added or taken off by score corrections, on top of the
+    /// passes.
+    #[serde(rename = "scoreAdjustment")]
+    score_adjustment: i32,
+}
+
+#[derive(Serialize)]
+struct JamDoc {
+    number: u8,
+    teams: BTreeMap<&'static str, TeamJamDoc>,
+}
+
+#[derive(Serialize)]
+struct PeriodDoc {
+    number: u8,
+    jams: Vec<JamDoc>,
+}
+
+#[derive(Serialize)]
+struct TimeoutDoc {
+    period: u8,
+    #[serde(rename = "afterJam")]
+    after_jam: u8,
+    /// `team`, `official` or `review`.
+    kind: TimeoutKind,
+    team: Option<&'static str>,
+}
+
+#[derive(Serialize)]
+pub struct GameDoc {
+    version: &'static str,
+    #[serde(rename = "type")]
+    doc_type: &'static str,
+    teams: BTreeMap<&'static str, derbyjson::Team>,
+    officials: Vec<derbyjson::Person>,
+    periods: Vec<PeriodDoc>,
+    timeouts: Vec<TimeoutDoc>,
+    /// The IGRF.
+    metadata: Igrf,
+    #[serde(rename = "final")]
+    is_final: bool,
+    /// When the game was set up, in milliseconds since the epoch, which
+    /// the archive knows it by.
+    created: u64,
+}
+
+#[derive(Serialize)]
+struct LiveGame {
+    /// The `since` to pass when polling for updates.
+    next: u64,
+    game: GameDoc,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "update", rename_all = "snake_case")]
+enum Update {
+    Jam { period: u8, jam: JamDoc },
+    /// The timeout at `index` in the document's `timeouts`, which is
+    /// either new or replaces the one there.
+    Timeout { index: usize, timeout: TimeoutDoc },
+    Reset,
+}
+
+#[derive(Serialize)]
+struct UpdateResult {
+    /// The `since` to pass on the next poll.
+    next: u64,
+    updates: Vec<Update>,
+}
+
+#[derive(FromForm)]
+struct UpdateQuery {
+    since: u64,
+}
+
+fn team_jam(game: &GameState, jam: &JamState, team: Team) -> TeamJamDoc {
+    let tj = &jam[team];
+    let skaters = &game.roster(team).skaters;
+    let number = |idx: usize| skaters.get(idx).map(|s| s.number.clone());
+    let mut lineup = Vec::new();
+    if let Some(n) = tj.jammer().and_then(&number) {
+        lineup.push(LineupDoc { skater: n, position: "jammer" });
+    }
+    if let Some(n) = tj.pivot().and_then(&number) {
+        lineup.push(LineupDoc { skater: n, position: "pivot" });
+    }
+    for idx in tj.blockers() {
+        if let Some(n) = number(idx) {
+            lineup.push(LineupDoc { skater: n, position: "blocker" });
+        }
+    }
+    TeamJamDoc {
+        lineup: lineup,
+        passes: tj.trips().into_iter().enumerate().map(|(i, points)| PassDoc {
+            number: i as u8 + 2, score: points, pivot: i >= tj.jammer_trips().len(),
+        }).collect(),
+        penalties: tj.penalties.iter().filter_map(|&(idx, code)| {
+            number(idx).map(|n| PenaltyDoc { skater: n, penalty: code.as_char() })
+        }).collect(),
+        lead: tj.lead(), lost: tj.lost(), call: tj.call(), star_pass: tj.starpass(),
+        score_adjustment: tj.score_adjust(),
+    }
+}
+
+fn jam_doc(game: &GameState, jamidx: usize) -> (u8, JamDoc) {
+    let jam = &game.jams()[jamidx];
+    let (period, number) = game.jamidx_to_periodjam(jamidx);
+    let mut teams = BTreeMap::new();
+    for &team in [Team::Home, Team::Away].iter() {
+        teams.insert(team_key(team), team_jam(game, jam, team));
+    }
+    (period, JamDoc { number: number, teams: teams })
+}
+
+fn timeout_doc(entry: &TimeoutEntry) -> TimeoutDoc {
+    TimeoutDoc {
+        period: entry.period, after_jam: entry.after_jam, kind: entry.kind,
+        team: entry.team.map(team_key),
+    }
+}
+
+pub fn game_doc(game: &GameState) -> GameDoc {
+    let mut periods: Vec<PeriodDoc> = Vec::new();
+    for idx in 0..game.jams().len() {
+        let (period, jam) = jam_doc(game, idx);
+        if periods.last().map_or(true, |p| p.number != period) {
+            periods.push(PeriodDoc { number: period, jams: Vec::new() });
+        }
+        periods.last_mut().unwrap().jams.push(jam);
+    }
+    let mut teams = BTreeMap::new();
+    for &team in [Team::Home, Team::Away].iter() {
+        teams.insert(team_key(team), game.roster(team).as_derbyjson());
+    }
+    GameDoc {
+        version: DERBYJSON_VERSION, doc_type: "game", teams: teams, periods: periods,
+        officials: game.officials().iter().map(Official::as_derbyjson).collect(),
+        timeouts: game.timeout_log().iter().map(timeout_doc).collect(),
+        metadata: game.igrf().clone(), is_final: game.final_at().is_some(),
+        created: game.created(),
+    }
+}
+
+/// Find the jam with the given period and jam number.
+fn find_jam(game: &GameState, period: u8, jam: u8) -> Option<usize> {
+    (0..game.jams().len()).rev().find(|&idx| game.jamidx_to_periodjam(idx) == (period, jam))
+}
+
+/// The update for an event, if it changes the document.
+fn update_for(game: &GameState, notice: &Notice) -> Option<Update> {
+    let (period, jam) = match notice.event {
+        Event::JamStarted { period, jam } | Event::JamEnded { period, jam, .. } |
+        Event::JamEndedByTime { period, jam } | Event::Trip { period, jam, .. } |
+        Event::Penalty { period, jam, .. } | Event::JamChanged { period, jam, .. } =>
+            (period, jam),
+        // Changes to the score that aren't trips, such as the jam timer's
+        // plus and minus buttons, are on the current jam.
+        Event::ScoreChanged { .. } => game.jamidx_to_periodjam(game.jams().len() - 1),
+        Event::Timeout { index, .. } => {
+            return game.timeout_log().get(index).map(|t| {
+                Update::Timeout { index: index, timeout: timeout_doc(t) }
+            });
+        },
+        Event::Correction { .. } => return Some(Update::Reset),
+        _ => return None,
+    };
+    find_jam(game, period, jam).map(|idx| {
+        let (period, jam) = jam_doc(game, idx);
+        Update::Jam { period: period, jam: jam }
+    })
+}
+
+#[get("/derbyjson/game")]
+fn live_game(game: Game) -> Json<LiveGame> {
+    Json(LiveGame { next: events::next_seq(), game: game_doc(&game) })
+}
+
+/// Wait a couple of seconds for something to happen, then send the
+/// updated pieces of the document, if any; an empty list just means
+/// nothing happened and the client should poll again. Each piece is sent
+/// as it is now, rather than as it was when the event happened, so a
+/// client that applies them in order ends up with the current game.
+#[get("/derbyjson/updates?<query>")]
+fn live_updates(query: UpdateQuery) -> Json<UpdateResult> {
+    let (next, notices, missed) = events::wait(query.since);
+    let game = guard::get_game();
+    let game = match *game {
+        Some(ref g) => g,
+        None => return Json(UpdateResult { next: next, updates: Vec::new() }),
+    };
+    let mut updates = Vec::new();
+    if missed {
+        updates.push(Update::Reset);
+    } else {
+        for notice in &notices {
+            if let Some(update) = update_for(game, notice) {
+                updates.push(update);
+            }
+        }
+    }
+    Json(UpdateResult { next: next, updates: updates })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use events::{Event, Notice};
+    use gamestate::GameState;
+    use gamestate::jamstate::Team;
+    use gamestate::ruleset::Ruleset;
+    use roster;
+    use super::{update_for, Update};
+
+    fn notice(event: Event) -> Notice {
+        Notice { seq: 0, time: 0, event: event }
+    }
+
+    #[test]
+    fn test_jam_updates() {
+        let mut game = GameState::new(roster::Team::new(String::from("Home")),
+                                      roster::Team::new(String::from("Away")),
+                                      Duration::new(0, 0), Ruleset::default());
+        game.start_jam();
+        game.take_events();
+
+        game.cur_jam_mut()[Team::Home].set_lead(true);
+        game.jam_changed(1, Team::Home);
+        let events = game.take_events();
+        assert_eq!(events.len(), 1);
+        match update_for(&game, &notice(events[0].clone())) {
+            Some(Update::Jam { period: 1, ref jam }) => {
+                assert_eq!(jam.number, 1);
+                assert!(jam.teams["home"].lead);
+            },
+            _ => panic!("no jam update for a change of lead"),
+        }
+
+        game.cur_jam_mut().adj_score(0, 2);
+        match update_for(&game, &notice(Event::ScoreChanged { score: (0, 2) })) {
+            Some(Update::Jam { period: 1, ref jam }) => {
+                assert_eq!(jam.number, 1);
+                assert_eq!(jam.teams["away"].passes.len(), 1);
+            },
+            _ => panic!("no jam update for a change of score"),
+        }
+    }
+}
@@ -0,0 +1,92 @@
+/*! Errors from the HTTP API. A failed request gets a JSON body like
+    `{"error": "no_such_jam", "message": "There is no jam 12"}`: `error` is
+    a fixed code for programs to match on, and `message` is for people.
+    The catchers here do the same for requests that fail before reaching a
+    handler, such as unknown routes or bodies that don't parse (which
+    `Json` turns away with a 400).
+ */
+
+use std::io;
+
+use rocket::Request;
+use rocket::http::Status;
+use rocket::response::{self, Responder, Response};
+use rocket_contrib::Json;
+
+use gamestate::jamstate::Team;
+
+#[derive(Serialize)]
+struct ErrorBody {
+    error: &'static str,
+    message: String,
+}
+
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: Status,
+    pub code: &'static str,
+    pub message: String,
+}
+
+pub type ApiResult<T> = Result<T, ApiError>;
+
+impl ApiError {
+    pub fn new<S: Into<String>>(status: Status, code: &'static str, message: S) -> ApiError {
+        ApiError { status: status, code: code, message: message.into() }
+    }
+
+    pub fn bad_request<S: Into<String>>(code: &'static str, message: S) -> ApiError {
+        ApiError::new(Status::BadRequest, code, message)
+    }
+
+    pub fn not_found<S: Into<String>>(code: &'static str, message: S) -> ApiError {
+        ApiError::new(Status::NotFound, code, message)
+    }
+}
+
+impl<'r> Responder<'r> for ApiError {
+    fn respond_to(self, request: &Request) -> response::Result<'r> {
+        let body = Json(ErrorBody { error: self.code, message: self.message });
+        Response::build_from(body.respond_to(request)?).status(self.status).ok()
+    }
+}
+
+impl From<io::Error> for ApiError {
+    fn from(e: io::Error) -> ApiError {
+        let message = format!("{}", e);
+        match e.kind() {
+            io::ErrorKind::NotFound => ApiError::not_found("not_found", message),
+            io::ErrorKind::InvalidInput | io::ErrorKind::InvalidData =>
+                ApiError::bad_request("invalid_input", message),
+            _ => ApiError::new(Status::InternalServerError, "io_error", message),
+        }
+    }
+}
+
+/// Check a team from the URL. Routes take the team as a `Result` so that
+/// a bad one gets this error instead of falling through to a 404.
+pub fn team(team: Result<Team, &'static str>) -> ApiResult<Team> {
+    team.map_err(|e| ApiError::bad_request("bad_team", e))
+}
+
+#[error(400)]
+fn bad_request(_: &Request) -> ApiError {
+    ApiError::bad_request("bad_request", "The request could not be understood")
+}
+
+#[error(404)]
+fn not_found(request: &Request) -> ApiError {
+    ApiError::not_found("not_found", format!("Nothing at {}", request.uri()))
+}
+
+/// The game state guards fail with this status when no game has been
+/// started.
+#[error(409)]
+fn no_game(_: &Request) -> ApiError {
+    ApiError::new(Status::Conflict, "no_game", "No game is in progress")
+}
+
+#[error(500)]
+fn internal_error(_: &Request) -> ApiError {
+    ApiError::new(Status::InternalServerError, "internal_error", "Something went wrong")
+}
@@ -0,0 +1,175 @@
+/*! The game event bus. `GameState` queues up events as things happen to
+    the clock, and they get published here whenever the game state lock is
+    released. In-process consumers (the log, a buzzer driver, ...) can
+    `subscribe` to get their own channel of events, and external devices
+    such as a horn controller can long-poll `/clock/events`.
+ */
+
+use std::collections::VecDeque;
+use std::sync::{Condvar, Mutex};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rocket_contrib::Json;
+
+use gamestate::Correction;
+use gamestate::jamstate::Team;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeoutKind {
+    Team,
+    Official,
+    Review,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Event {
+    JamStarted { period: u8, jam: u8 },
+    JamEndedByTime { period: u8, jam: u8 },
+    FiveSecondsToJam,
+    TimeoutWarning,
+    PeriodEnded { period: u8 },
+    GameOver,
+    JamEnded { period: u8, jam: u8, score: (u32, u32), jamscore: (u32, u32) },
+    ScoreChanged { score: (u32, u32) },
+    /// A timeout started, or was changed to another kind. `index` is its
+    /// place in the game's list of timeouts.
+    Timeout { index: usize, kind: TimeoutKind, team: Option<Team> },
+    TimeoutEnded,
+    ReviewOutcome { team: Team, retained: bool },
+    Correction { correction: Correction },
+    Trip { team: Team, period: u8, jam: u8, trip: u8, points: u8 },
+    Penalty { team: Team, period: u8, jam: u8, skater: String, code: char },
+    /// Something other than a trip or a penalty changed in a team's jam:
+    /// lead, lost, call, star pass, the lineup, box trips or no pivot.
+    JamChanged { team: Team, period: u8, jam: u8 },
+}
+
+impl Event {
+    /// The name the event is serialized under.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Event::JamStarted { .. } => "jam_started",
+            Event::JamEndedByTime { .. } => "jam_ended_by_time",
+            Event::FiveSecondsToJam => "five_seconds_to_jam",
+            Event::TimeoutWarning => "timeout_warning",
+            Event::PeriodEnded { .. } => "period_ended",
+            Event::GameOver => "game_over",
+            Event::JamEnded { .. } => "jam_ended",
+            Event::ScoreChanged { .. } => "score_changed",
+            Event::Timeout { .. } => "timeout",
+            Event::TimeoutEnded => "timeout_ended",
+            Event::ReviewOutcome { .. } => "review_outcome",
+            Event::Correction { .. } => "correction",
+            Event::Trip { .. } => "trip",
+            Event::Penalty { .. } => "penalty",
+            Event::JamChanged { .. } => "jam_changed",
+        }
+    }
+}
+
+/// An event as seen by subscribers, with a sequence number and the time
+/// it was published, in milliseconds since the epoch.
+#[derive(Serialize, Clone, Debug)]
+pub struct Notice {
+    pub seq: u64,
+    pub time: u64,
+    pub event: Event,
+}
+
+/// How many past events to keep around for pollers that fell behind.
+const HISTORY_LEN: usize = 256;
+/// How long a poller waits for something to happen before giving up. This
+/// is kept short, as each waiting poller ties up one of the server's few
+/// worker threads.
+const POLL_TIMEOUT: u64 = 2;
+
+struct Bus {
+    next_seq: u64,
+    history: VecDeque<Notice>,
+    subscribers: Vec<Sender<Notice>>,
+}
+
+pub fn now_millis() -> u64 {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    now.as_secs() * 1000 + (now.subsec_nanos() / 1_000_000) as u64
+}
+
+pub fn publish(event: Event) {
+    let mut bus = BUS.lock().unwrap();
+    let notice = Notice { seq: bus.next_seq, time: now_millis(), event: event };
+    bus.next_seq += 1;
+    if bus.history.len() == HISTORY_LEN {
+        bus.history.pop_front();
+    }
+    bus.history.push_back(notice.clone());
+    // Drop any subscribers that have gone away.
+    bus.subscribers.retain(|s| s.send(notice.clone()).is_ok());
+    NEW_EVENT.notify_all();
+}
+
+/// Get a channel that receives every event published from now on.
+pub fn subscribe() -> Receiver<Notice> {
+    let (tx, rx) = channel();
+    BUS.lock().unwrap().subscribers.push(tx);
+    rx
+}
+
+/// Start a thread that logs every event to stdout.
+pub fn spawn_logger() {
+    let rx = subscribe();
+    thread::spawn(move || {
+        for notice in rx {
+            println!("event {}: {:?}", notice.seq, notice.event);
+        }
+    });
+}
+
+#[derive(FromForm)]
+struct PollQuery {
+    since: u64,
+}
+
+#[derive(Serialize)]
+struct PollResult {
+    /// The `since` to pass on the next poll.
+    next: u64,
+    events: Vec<Notice>,
+}
+
+/// Wait for events with a sequence number of at least `since`, and return
+/// them. Returns an empty list if nothing happens for a while, in which
+/// case the client should just poll again.
+#[get("/clock/events?<query>")]
+fn poll_events(query: PollQuery) -> Json<PollResult> {
+    let (next, events, _) = wait(query.since);
+    Json(PollResult { next: next, events: events })
+}
+
+/// Wait for events with a sequence number of at least `since`, giving up
+/// after a while. Returns the next sequence number, the events, and
+/// whether any of the events asked for have already been forgotten.
+pub fn wait(since: u64) -> (u64, Vec<Notice>, bool) {
+    let mut bus = BUS.lock().unwrap();
+    if bus.next_seq <= since {
+        bus = NEW_EVENT.wait_timeout(bus, Duration::new(POLL_TIMEOUT, 0)).unwrap().0;
+    }
+    let missed = bus.history.front().map_or(bus.next_seq > since, |n| n.seq > since);
+    let events = bus.history.iter().filter(|n| n.seq >= since).cloned().collect();
+    (bus.next_seq, events, missed)
+}
+
+/// The sequence number the next event will get.
+pub fn next_seq() -> u64 {
+    BUS.lock().unwrap().next_seq
+}
+
+lazy_static! {
+    static ref BUS: Mutex<Bus> = Mutex::new(Bus {
+        next_seq: 0, history: VecDeque::new(), subscribers: Vec::new(),
+    });
+    static ref NEW_EVENT: Condvar = Condvar::new();
+}
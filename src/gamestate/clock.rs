@@ -6,6 +6,8 @@
 
 use std::time::{Instant, Duration};
 
+use super::ruleset::Ruleset;
+
 #[derive(Clone, Copy, PartialEq)]
 pub enum Clocktype {
     Jam,
@@ -47,26 +49,27 @@ pub struct Clock {
     clocktype: Clocktype,
     activeclock: Duration,
     lastupdate: Instant,
+    rules: Ruleset,
 }
 
 /* tick is called on every tick. Also,
  * every command has an implicit tick. */
 impl Clock {
-    pub fn new(ttd: Duration) -> Clock {
+    pub fn new(ttd: Duration, rules: Ruleset) -> Clock {
         Clock {
-            gameclock: Duration::new(30*60, 0),
+            gameclock: rules.period_length,
             period: 0,
             clocktype: Clocktype::Intermission,
             activeclock: ttd,
             lastupdate: Instant::now(),
+            rules: rules,
         }
     }
     fn clock_start_amt(&self, ty: Clocktype) -> Duration {
-        // TODO: use clock policy
         match ty {
-            Clocktype::Jam => Duration::new(120, 0),
-            Clocktype::Lineup => Duration::new(30, 0),
-            Clocktype::Intermission => Duration::new(10 * 60, 0),
+            Clocktype::Jam => self.rules.jam_length,
+            Clocktype::Lineup => self.rules.lineup_length,
+            Clocktype::Intermission => self.rules.intermission_length,
             _ => Duration::new(0, 0),
         }
     }
@@ -126,17 +129,27 @@ impl Clock {
     // Valid when clock is any but Jam.
     //  * -> Jam
     pub fn start_jam(&mut self) -> () {
+        self.start_jam_at(Duration::new(0, 0));
+    }
+
+    /// Start a jam as if it had started `lag` ago, e.g. when the command
+    /// to start it was delayed on the way here.
+    pub fn start_jam_at(&mut self, lag: Duration) -> () {
         self.tick();
+        if let Clocktype::Jam = self.clocktype {
+            // don't do anything, it's already running.
+            return;
+        }
+        let game_clock_was_running = self.clocktype.game_clock_runs();
         if let Clocktype::Intermission = self.clocktype {
             self.period += 1;
-            self.gameclock = Duration::new(30 * 60, 0);
+            self.gameclock = self.rules.period_length;
+        }
+        self.start_clock(Clocktype::Jam, Some(lag));
+        // The game clock should have been running since the jam started too.
+        if !game_clock_was_running {
+            self.gameclock = self.gameclock.checked_sub(lag).unwrap_or(Duration::new(0, 0));
         }
-
-        if let Clocktype::Jam = self.clocktype {
-            // don't do anything, it's already running.
-        } else {
-            self.start_clock(Clocktype::Jam, None);
-        };
     }
 
     // {Jam, Lineup, OtherTimeout} -> TeamTimeout
@@ -156,7 +169,7 @@ impl Clock {
             Clocktype::Jam | Clocktype::Lineup => {
                 self.start_clock(Clocktype::OtherTimeout, None);
             },
-            Clocktype::TeamTimeout => { /* convert to an other timeout */ },
+            Clocktype::TeamTimeout => self.convert_timeout(Clocktype::OtherTimeout),
             Clocktype::Intermission | Clocktype::OtherTimeout | Clocktype::None =>  {
                 
                 // can't start Other Timeout
@@ -164,12 +177,35 @@ impl Clock {
         }
     }
 
-    // pub fn end_timeout(&mut self) -> ActiveClock {}
+    // {TeamTimeout, OtherTimeout} -> Lineup
+    pub fn end_timeout(&mut self) -> () {
+        match self.clocktype {
+            Clocktype::TeamTimeout | Clocktype::OtherTimeout => {
+                self.start_clock(Clocktype::Lineup, None);
+            },
+            _ => (), // no timeout to end
+        }
+    }
+
+    // TeamTimeout <-> OtherTimeout, keeping the time already run.
+    pub fn convert_timeout(&mut self, ty: Clocktype) -> () {
+        match (self.clocktype, ty) {
+            (Clocktype::TeamTimeout, Clocktype::OtherTimeout) |
+            (Clocktype::OtherTimeout, Clocktype::TeamTimeout) => self.clocktype = ty,
+            _ => (),
+        }
+    }
+
     // Jam -> Lineup
     pub fn stop_jam(&mut self) -> () {
+        self.stop_jam_at(Duration::new(0, 0));
+    }
+
+    /// Stop the jam as if it had stopped `lag` ago.
+    pub fn stop_jam_at(&mut self, lag: Duration) -> () {
         match self.clocktype {
             Clocktype::Jam => {
-                self.start_clock(Clocktype::Lineup, None);
+                self.start_clock(Clocktype::Lineup, Some(lag));
             },
             _ => (), // Jam not running, can't stop.
 
@@ -182,16 +218,27 @@ impl Clock {
     pub fn set_time(&mut self, time: Duration) {
         self.gameclock = time;
     }
+    pub fn set_active_time(&mut self, time: Duration) {
+        self.activeclock = time;
+    }
+    pub fn set_period(&mut self, period: u8) {
+        self.period = period;
+    }
 
     pub fn get_active_clock(&self) -> (Clocktype, Duration) {
         (self.clocktype, self.activeclock)
     }
+
+    /// When the clock values were last brought up to date.
+    pub fn last_update(&self) -> Instant {
+        self.lastupdate
+    }
 }
 
 #[cfg(test)]
 #[test]
 fn test_jam_start() {
-    let mut clock = Clock::new();
+    let mut clock = Clock::new(Duration::new(0, 0), Ruleset::default());
     clock.start_jam();
     // check that jam started
     // sleep 1s
@@ -204,7 +251,7 @@ fn test_jam_start() {
 #[test]
 fn test_jam_end() {
     use std::thread;
-    let mut clock = Clock::new();
+    let mut clock = Clock::new(Duration::new(0, 0), Ruleset::default());
     clock.start_jam();
     assert!(clock.clocktype == Clocktype::Jam);
     clock.activeclock = Duration::new(0, 500_000_000);
@@ -218,7 +265,7 @@ fn test_jam_end() {
 #[test]
 fn test_timeout_expires() {
     use std::thread;
-    let mut clock = Clock::new();
+    let mut clock = Clock::new(Duration::new(0, 0), Ruleset::default());
     clock.start_jam();
     clock.team_timeout();
     clock.activeclock = Duration::new(89, 500_000_000);
@@ -226,3 +273,30 @@ fn test_timeout_expires() {
     clock.tick();
     assert!(clock.clocktype == Clocktype::OtherTimeout);
 }
+
+#[cfg(test)]
+#[test]
+fn test_late_jam_start() {
+    let mut clock = Clock::new(Duration::new(0, 0), Ruleset::default());
+    clock.start_jam_at(Duration::new(3, 0));
+    assert!(clock.clocktype == Clocktype::Jam);
+    assert!(clock.activeclock <= Duration::new(117, 0));
+    assert!(clock.gameclock <= Duration::new(30 * 60 - 3, 0));
+    clock.stop_jam_at(Duration::new(2, 0));
+    assert!(clock.clocktype == Clocktype::Lineup);
+    assert!(clock.activeclock <= Duration::new(28, 0));
+}
+
+#[cfg(test)]
+#[test]
+fn test_end_timeout() {
+    let mut clock = Clock::new(Duration::new(0, 0), Ruleset::default());
+    clock.start_jam();
+    clock.team_timeout();
+    clock.activeclock = Duration::new(20, 0);
+    clock.convert_timeout(Clocktype::OtherTimeout);
+    assert!(clock.clocktype == Clocktype::OtherTimeout);
+    assert!(clock.activeclock == Duration::new(20, 0));
+    clock.end_timeout();
+    assert!(clock.clocktype == Clocktype::Lineup);
+}
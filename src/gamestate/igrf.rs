@@ -0,0 +1,127 @@
+/*! The Interleague Game Reporting Form: everything about a game that isn't
+    on the score sheets, such as where it was played, who skated as
+    captain, who officiated and who signed off on the result. A game can
+    only be marked final once the form is complete.
+ */
+
+use std::fmt;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum GameType {
+    Sanctioned,
+    Regulation,
+    Other,
+}
+
+impl Default for GameType {
+    fn default() -> GameType { GameType::Other }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+#[serde(default)]
+pub struct Venue {
+    pub name: String,
+    pub city: String,
+    pub state: String,
+    pub country: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+#[serde(default)]
+pub struct IgrfTeam {
+    pub league: String,
+    pub captain: String,
+    pub alternate: String,
+}
+
+/// Signatures are the signer's name as typed on the form.
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+#[serde(default)]
+pub struct Signatures {
+    pub home_captain: String,
+    pub away_captain: String,
+    pub head_referee: String,
+    pub head_nso: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+#[serde(default)]
+pub struct Igrf {
+    pub venue: Venue,
+    /// As `YYYY-MM-DD`.
+    pub date: String,
+    /// As `HH:MM`.
+    pub start_time: String,
+    pub game_type: GameType,
+    /// The sanctioning body's game number, for sanctioned games.
+    pub game_number: String,
+    pub tournament: String,
+    pub host_league: String,
+    pub home: IgrfTeam,
+    pub away: IgrfTeam,
+    pub signatures: Signatures,
+    pub notes: String,
+}
+
+/// A field that has to be filled in before the game can be final.
+#[derive(Debug, PartialEq)]
+pub struct Missing(pub &'static str);
+
+impl fmt::Display for Missing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} is required", self.0)
+    }
+}
+
+fn is_date(s: &str) -> bool {
+    let parts = s.split('-').collect::<Vec<_>>();
+    parts.len() == 3 && parts[0].len() == 4 && parts[1].len() == 2 && parts[2].len() == 2 &&
+        parts.iter().all(|p| p.chars().all(|c| c.is_digit(10)))
+}
+
+impl Igrf {
+    /// Everything that still needs filling in.
+    pub fn missing(&self) -> Vec<Missing> {
+        let required = [
+            (&self.venue.name, "Venue name"),
+            (&self.venue.city, "Venue city"),
+            (&self.venue.country, "Venue country"),
+            (&self.home.league, "Home league"),
+            (&self.home.captain, "Home captain"),
+            (&self.away.league, "Away league"),
+            (&self.away.captain, "Away captain"),
+            (&self.signatures.home_captain, "Home captain's signature"),
+            (&self.signatures.away_captain, "Away captain's signature"),
+            (&self.signatures.head_referee, "Head referee's signature"),
+            (&self.signatures.head_nso, "Head NSO's signature"),
+        ];
+        let mut missing = required.iter().filter(|&&(value, _)| value.trim().is_empty())
+            .map(|&(_, name)| Missing(name)).collect::<Vec<_>>();
+        if !is_date(&self.date) {
+            missing.push(Missing("Date"));
+        }
+        if self.game_type == GameType::Sanctioned && self.game_number.trim().is_empty() {
+            missing.push(Missing("Game number"));
+        }
+        missing
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing() {
+        let mut igrf = Igrf::default();
+        assert!(igrf.missing().contains(&Missing("Venue name")));
+        assert!(!igrf.missing().contains(&Missing("Game number")));
+        igrf.game_type = GameType::Sanctioned;
+        assert!(igrf.missing().contains(&Missing("Game number")));
+        igrf.date = String::from("2017-13");
+        assert!(igrf.missing().contains(&Missing("Date")));
+        igrf.date = String::from("2017-09-30");
+        assert!(!igrf.missing().contains(&Missing("Date")));
+    }
+}
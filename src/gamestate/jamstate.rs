@@ -4,11 +4,77 @@ use rocket::http::RawStr;
 
 use super::penaltycodes::*;
 
+/// Lineup slots, as on the lineup sheet: the jammer, the pivot, and
+/// three blockers.
+pub const LINEUP_SLOTS: usize = 5;
+
+/// A box trip as marked on the lineup sheet.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum BoxMark {
+    /// Went to the box during the jam.
+    #[serde(rename = "/")]
+    Entered,
+    /// Went to the box and was released during the jam.
+    #[serde(rename = "X")]
+    EnteredReleased,
+    /// Started the jam in the box.
+    #[serde(rename = "S")]
+    Started,
+    /// Started the jam in the box and was released during it.
+    #[serde(rename = "$")]
+    StartedReleased,
+    /// Sent to the box after the jam ended.
+    #[serde(rename = "+")]
+    AfterJam,
+    /// Didn't take the track.
+    #[serde(rename = "-")]
+    Absent,
+    /// Injured, and out for the next three jams.
+    #[serde(rename = "3")]
+    Injured,
+}
+
+impl BoxMark {
+    /// The mark as written on the sheet.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            BoxMark::Entered => "/",
+            BoxMark::EnteredReleased => "X",
+            BoxMark::Started => "S",
+            BoxMark::StartedReleased => "$",
+            BoxMark::AfterJam => "+",
+            BoxMark::Absent => "-",
+            BoxMark::Injured => "3",
+        }
+    }
+    /// Whether the skater is still in the box after this.
+    fn in_box(&self) -> bool {
+        match *self {
+            BoxMark::Entered | BoxMark::Started | BoxMark::AfterJam => true,
+            _ => false,
+        }
+    }
+    /// Whether the skater started the jam in the box.
+    fn started(&self) -> bool {
+        match *self {
+            BoxMark::Started | BoxMark::StartedReleased => true,
+            _ => false,
+        }
+    }
+}
+
 #[derive(Default,Serialize,Clone)]
 pub struct TeamJamState {
-    lineup: [u32; 6],
+    lineup: [u32; LINEUP_SLOTS],
+    /// The box trips of the skater in each lineup slot.
+    boxtrips: [Vec<BoxMark>; LINEUP_SLOTS],
+    /// No pivot this jam, so the pivot slot holds a fourth blocker.
+    no_pivot: bool,
     jammerpoints: Vec<u8>,
     pivotpoints: Vec<u8>,
+    /// Points added or taken off by score corrections, which don't belong
+    /// to any one trip.
+    score_adjust: i32,
     pub penalties: Vec<(usize, PenaltyType)>,
     starpass: bool,
     lead: bool,
@@ -16,7 +82,7 @@ pub struct TeamJamState {
     call: bool,
 }
 
-#[derive(Serialize, Deserialize, Clone, Copy)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
 pub enum Team {
     Home = 1,
     Away = 2,
@@ -52,19 +118,132 @@ impl TeamJamState {
             self.lost = true;
         }
     }
+    /// Set the points for a scoring trip. Trips are numbered as on the
+    /// score sheet, with trip 1 the initial trip, and the pivot's trips
+    /// after a star pass carry on from the jammer's.
     pub fn set_score(&mut self, trip: u8, points: u8) {
-        let idx = trip as usize;
-        let points = if !self.starpass {
-            &self.jammerpoints
+        if trip < 2 {
+            return;
+        }
+        let mut idx = trip as usize - 2;
+        let pointvec = if !self.starpass || idx < self.jammerpoints.len() {
+            &mut self.jammerpoints
         } else {
-            &self.pivotpoints
+            idx -= self.jammerpoints.len();
+            &mut self.pivotpoints
         };
+        if pointvec.len() <= idx {
+            pointvec.resize(idx + 1, 0);
+        }
+        pointvec[idx] = points;
+    }
+    /// The jammer's points for each scoring trip, from trip 2 on.
+    pub fn jammer_trips(&self) -> &[u8] { &self.jammerpoints }
+    /// After a star pass, the pivot's points for each trip, carrying on
+    /// from the jammer's.
+    pub fn pivot_trips(&self) -> &[u8] { &self.pivotpoints }
+    /// The points for each scoring trip, from trip 2 on.
+    pub fn trips(&self) -> Vec<u8> {
+        self.jammerpoints.iter().chain(self.pivotpoints.iter()).cloned().collect()
+    }
+    pub fn lead(&self) -> bool { self.lead }
+    pub fn lost(&self) -> bool { self.lost }
+    pub fn call(&self) -> bool { self.call }
+    pub fn starpass(&self) -> bool { self.starpass }
+    pub fn jammer_points(&self) -> u32 {
+        self.jammerpoints.iter().map(|&p| p as u32).sum()
+    }
+    pub fn pivot_points(&self) -> u32 {
+        self.pivotpoints.iter().map(|&p| p as u32).sum()
+    }
+    pub fn score_adjust(&self) -> i32 { self.score_adjust }
+    pub fn set_score_adjust(&mut self, adjust: i32) { self.score_adjust = adjust }
+    /// The team's points for the jam: its trips, plus any corrections.
+    pub fn points(&self) -> u32 {
+        let points = (self.jammer_points() + self.pivot_points()) as i32 + self.score_adjust;
+        if points < 0 { 0 } else { points as u32 }
+    }
+    /// Lineup slots hold a roster index plus one, with 0 meaning the slot
+    /// is empty. Slot 0 is the jammer and slot 1 the pivot.
+    pub fn jammer(&self) -> Option<usize> { lineup_slot(self.lineup[0]) }
+    pub fn pivot(&self) -> Option<usize> {
+        if self.no_pivot { None } else { lineup_slot(self.lineup[1]) }
+    }
+    /// The blockers, leaving out the pivot unless there's no pivot this
+    /// jam.
+    pub fn blockers(&self) -> Vec<usize> {
+        let from = if self.no_pivot { 1 } else { 2 };
+        self.lineup[from..].iter().filter_map(|&s| lineup_slot(s)).collect()
+    }
+    /// The skater, by roster index, in a lineup slot.
+    pub fn lineup_slot(&self, slot: usize) -> Option<usize> { lineup_slot(self.lineup[slot]) }
+    /// Put a skater, by roster index, in a lineup slot, or empty it.
+    pub fn set_lineup_slot(&mut self, slot: usize, skater: Option<usize>) {
+        self.lineup[slot] = skater.map_or(0, |idx| idx as u32 + 1);
+    }
+    pub fn no_pivot(&self) -> bool { self.no_pivot }
+    pub fn set_no_pivot(&mut self, yes: bool) { self.no_pivot = yes }
+    pub fn box_trips(&self, slot: usize) -> &[BoxMark] { &self.boxtrips[slot] }
+    pub fn set_box_trips(&mut self, slot: usize, marks: Vec<BoxMark>) {
+        self.boxtrips[slot] = marks;
+    }
+    /// The start of the next jam's lineup: skaters still in the box at the
+    /// end of this jam start the next one there, in the same position.
+    pub fn carried_over(&self) -> TeamJamState {
+        let mut next = TeamJamState::default();
+        for slot in 0..LINEUP_SLOTS {
+            if self.boxtrips[slot].last().map_or(false, |m| m.in_box()) {
+                next.lineup[slot] = self.lineup[slot];
+                next.boxtrips[slot].push(BoxMark::Started);
+            }
+        }
+        next
+    }
+    /// Bring the start of this jam into line with what the last one
+    /// carried over, after the last one's lineup or box trips have been
+    /// corrected. Carried skaters take their slot, starting in the box; a
+    /// slot that no longer carries anybody over loses its start in the
+    /// box, and its skater too if that was all there was to them.
+    pub fn take_carried(&mut self, carried: &TeamJamState) {
+        for slot in 0..LINEUP_SLOTS {
+            let started = self.boxtrips[slot].first().map_or(false, |m| m.started());
+            if carried.lineup[slot] != 0 {
+                self.lineup[slot] = carried.lineup[slot];
+                if !started {
+                    self.boxtrips[slot].insert(0, BoxMark::Started);
+                }
+            } else if started {
+                self.boxtrips[slot].remove(0);
+                if self.boxtrips[slot].is_empty() {
+                    self.lineup[slot] = 0;
+                }
+            }
+        }
+    }
+    /// Whether nothing has happened to the team in the jam beyond being
+    /// lined up, as in the jam after the last one played.
+    pub fn lined_up_only(&self) -> bool {
+        self.jammerpoints.is_empty() && self.pivotpoints.is_empty() && self.score_adjust == 0
+            && self.penalties.is_empty() && !self.lead && !self.lost && !self.call
+            && !self.starpass
+            && self.boxtrips.iter().all(|marks| marks.iter().all(|&m| m == BoxMark::Started))
+    }
+    /// Record a penalty for a skater, by roster index. Returns false if the
+    /// code isn't a penalty.
+    pub fn add_penalty(&mut self, skater: usize, code: char) -> bool {
+        match PenaltyType::from_char(code) {
+            PenaltyType::Unknown => false,
+            penalty => { self.penalties.push((skater, penalty)); true },
+        }
     }
     pub fn set_lead(&mut self, yes: bool) { self.lead = yes }
     pub fn set_call(&mut self, yes: bool) { self.call = yes }
     pub fn set_lost(&mut self, yes: bool) { self.lost = yes; if self.lost { self.lead = false } }
 }
 
+fn lineup_slot(slot: u32) -> Option<usize> {
+    if slot == 0 { None } else { Some(slot as usize - 1) }
+}
 
 #[derive(Default)]
 pub struct JamState {
@@ -72,6 +251,9 @@ pub struct JamState {
     pub team2: TeamJamState,
     pub starttime: Option<Instant>,
     pub endtime: Option<Instant>,
+    /// Played, but with no record of when, such as a jam imported from
+    /// another system. Its times stay unset.
+    pub untimed: bool,
 }
 
 use std::ops::{Index,IndexMut};
@@ -96,15 +278,68 @@ impl IndexMut<Team> for JamState {
 }
 
 impl JamState {
+    /// Whether the jam has been started, or was played somewhere else.
+    pub fn played(&self) -> bool {
+        self.untimed || self.starttime.is_some()
+    }
     pub fn jam_score(&self) -> (u32, u32) {
-        let p1j = self.team1.jammerpoints.iter().sum::<u8>();
-        let p1p = self.team1.pivotpoints.iter().sum::<u8>();
-        let p2j = self.team2.jammerpoints.iter().sum::<u8>();
-        let p2p = self.team2.pivotpoints.iter().sum::<u8>();
-        (p1j as u32 + p1p as u32, p2j as u32 + p2p as u32)
+        (self.team1.points(), self.team2.points())
     }
     pub fn adj_score(&mut self, t1adj: i8, t2adj: i8) -> () {
         self.team1.update_points(t1adj);
         self.team2.update_points(t2adj);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{BoxMark, TeamJamState};
+
+    #[test]
+    fn test_star_pass_trips() {
+        let mut tj = TeamJamState::default();
+        tj.set_score(2, 4);
+        tj.set_score(3, 4);
+        tj.set_starpass(true);
+        tj.set_score(4, 3);
+        assert_eq!(tj.jammer_points(), 8);
+        assert_eq!(tj.pivot_points(), 3);
+        assert_eq!(tj.trips(), vec![4, 4, 3]);
+    }
+
+    #[test]
+    fn test_carried_over() {
+        let mut tj = TeamJamState::default();
+        tj.set_lineup_slot(0, Some(3));
+        tj.set_lineup_slot(2, Some(5));
+        tj.set_lineup_slot(3, Some(7));
+        tj.set_box_trips(0, vec![BoxMark::Entered]);
+        tj.set_box_trips(2, vec![BoxMark::EnteredReleased]);
+        let next = tj.carried_over();
+        assert_eq!(next.jammer(), Some(3));
+        assert_eq!(next.box_trips(0), &[BoxMark::Started]);
+        assert_eq!(next.lineup_slot(2), None);
+        assert_eq!(next.lineup_slot(3), None);
+    }
+
+    #[test]
+    fn test_take_carried() {
+        let mut tj = TeamJamState::default();
+        tj.set_lineup_slot(2, Some(5));
+        tj.set_box_trips(2, vec![BoxMark::AfterJam]);
+        let mut next = TeamJamState::default();
+        next.set_lineup_slot(0, Some(1));
+        next.set_lineup_slot(2, Some(8));
+        next.set_box_trips(0, vec![BoxMark::StartedReleased, BoxMark::Entered]);
+        next.take_carried(&tj.carried_over());
+        assert_eq!(next.lineup_slot(2), Some(5));
+        assert_eq!(next.box_trips(2), &[BoxMark::Started]);
+        assert_eq!(next.jammer(), Some(1));
+        assert_eq!(next.box_trips(0), &[BoxMark::Entered]);
+
+        tj.set_box_trips(2, vec![]);
+        next.take_carried(&tj.carried_over());
+        assert_eq!(next.lineup_slot(2), None);
+        assert_eq!(next.box_trips(2), &[] as &[BoxMark]);
+    }
+}
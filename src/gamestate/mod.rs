@@ -1,7 +1,10 @@
 mod clock;
+pub mod igrf;
 pub mod jamstate;
 mod penaltycodes;
+pub mod ruleset;
 mod savestate;
+pub mod stats;
 
 
 use std::collections::HashMap;
@@ -9,7 +12,12 @@ use std::iter::FromIterator;
 
 use self::penaltycodes::*;
 use self::jamstate::*;
+use self::ruleset::Ruleset;
+use self::igrf::{Igrf, Missing};
+use officials::{Official, Operator};
 use roster;
+use events::{self, Event, TimeoutKind};
+use std::mem;
 use std::time::*;
 use std::ops::{Index,IndexMut};
 
@@ -21,8 +29,8 @@ pub struct TeamState {
 }
 
 impl TeamState {
-    fn new(roster: roster::Team) -> TeamState {
-        TeamState { timeouts: 3, reviews: 2, roster: roster }
+    fn new(roster: roster::Team, rules: &Ruleset) -> TeamState {
+        TeamState { timeouts: rules.timeouts, reviews: rules.reviews, roster: roster }
     }
 }
 
@@ -32,6 +40,12 @@ pub struct Penalty {
     code: PenaltyType,
 }
 
+impl Penalty {
+    /// The period and jam the penalty was called in.
+    pub fn jam(&self) -> (u8, u8) { self.jam }
+    pub fn code(&self) -> char { self.code.as_char() }
+}
+
 #[allow(non_camel_case_types)]
 #[derive(Serialize)]
 pub enum ActiveClock {
@@ -45,6 +59,82 @@ pub enum ActiveClock {
     none,
 }
 
+/// The clocks to the millisecond, along with which of them are running, so
+/// that displays can keep them moving smoothly between updates.
+#[derive(Serialize)]
+pub struct ClockState {
+    pub game_ms: u64,
+    pub active_ms: u64,
+    pub game_running: bool,
+    pub active_running: bool,
+    pub active_counts_down: bool,
+}
+
+/// A correction made by an operator, with the old and new values. Clock
+/// values are in milliseconds.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Correction {
+    PeriodClock { from: u64, to: u64 },
+    ActiveClock { from: u64, to: u64 },
+    Period { from: u8, to: u8 },
+    Jam { from: u8, to: u8 },
+    Timeouts { team: Team, from: u8, to: u8 },
+    Reviews { team: Team, from: u8, to: u8 },
+    Score { team: Team, from: u32, to: u32 },
+}
+
+/// A correction as kept in the game history: when it was made, in
+/// milliseconds since the epoch, and where in the game.
+#[derive(Serialize, Clone)]
+pub struct CorrectionEntry {
+    pub time: u64,
+    pub period: u8,
+    pub jam: u8,
+    pub correction: Correction,
+}
+
+/// A timeout as kept in the game history. `after_jam` is the jam that was
+/// over or cut short when it was called, or 0 before the first jam.
+#[derive(Serialize, Clone)]
+pub struct TimeoutEntry {
+    pub period: u8,
+    pub after_jam: u8,
+    pub kind: TimeoutKind,
+    pub team: Option<Team>,
+}
+
+/// Something an official did, as the event it caused, and when.
+#[derive(Serialize, Clone)]
+pub struct ActionEntry {
+    pub time: u64,
+    pub by: Operator,
+    pub event: Event,
+}
+
+fn millis(d: Duration) -> u64 {
+    d.as_secs() * 1000 + (d.subsec_nanos() / 1_000_000) as u64
+}
+
+fn adjust(n: u32, by: i32) -> u32 {
+    let n = n as i32 + by;
+    if n < 0 { 0 } else { n as u32 }
+}
+
+pub enum PenaltyError {
+    UnknownSkater,
+    UnknownCode,
+    NoSuchJam,
+}
+
+pub enum FinalError {
+    /// The game is still being played.
+    NotOver,
+    /// Things the paperwork is still missing.
+    Incomplete(Vec<Missing>),
+}
+
+#[derive(Clone, Copy)]
 enum ActiveTimeout {
     None, TeamTO(Team), Official, Review(Team), Halftime, TimeToDerby,
 }
@@ -56,6 +146,25 @@ pub struct GameState {
     tostate: ActiveTimeout,
     jams: Vec<JamState>,
     second_period_start: usize,
+    /// Where the second period started before a correction moved the game
+    /// back to period 1, so that moving it on again puts the boundary back.
+    earlier_second_period_start: Option<usize>,
+    /// Added to the jam numbers of each period by jam number corrections.
+    jam_offsets: [i16; 2],
+    corrections: Vec<CorrectionEntry>,
+    timeout_log: Vec<TimeoutEntry>,
+    /// An imported game being corrected after the fact. Its clocks don't
+    /// run.
+    editing: bool,
+    /// When the game was set up, in milliseconds since the epoch.
+    created: u64,
+    igrf: Igrf,
+    /// When the game was marked final, after which the IGRF can't change.
+    final_at: Option<u64>,
+    officials: Vec<Official>,
+    actions: Vec<ActionEntry>,
+    events: Vec<Event>,
+    rules: Ruleset,
 }
 
 impl Index<Team> for GameState {
@@ -86,15 +195,99 @@ impl IndexMut<Team> for GameState {
 
 impl GameState {
     pub fn new(roster1: roster::Team, roster2: roster::Team,
-           time_to_derby: Duration) -> GameState {
+           time_to_derby: Duration, rules: Ruleset) -> GameState {
         let firstjam = JamState::default();
-        let team1 = TeamState::new(roster1);
-        let team2 = TeamState::new(roster2);
+        let team1 = TeamState::new(roster1, &rules);
+        let team2 = TeamState::new(roster2, &rules);
         GameState { jams: vec![firstjam], team1: team1, team2: team2,
-                    clock: clock::Clock::new(time_to_derby), second_period_start: 0,
-                    tostate: ActiveTimeout::TimeToDerby,
+                    clock: clock::Clock::new(time_to_derby, rules), second_period_start: 0,
+                    earlier_second_period_start: None, jam_offsets: [0, 0],
+                    corrections: Vec::new(), timeout_log: Vec::new(),
+                    tostate: ActiveTimeout::TimeToDerby, editing: false,
+                    created: events::now_millis(), igrf: Igrf::default(), final_at: None,
+                    officials: Vec::new(), actions: Vec::new(), events: Vec::new(),
+                    rules: rules,
+        }
+    }
+    /// A game scored elsewhere, for editing. `jams` are all the jams of the
+    /// game, with the second period starting at `second_period_start`, or 0
+    /// if there was only one period.
+    pub fn imported(roster1: roster::Team, roster2: roster::Team, rules: Ruleset,
+                    mut jams: Vec<JamState>, second_period_start: usize,
+                    timeout_log: Vec<TimeoutEntry>) -> GameState {
+        let mut game = GameState::new(roster1, roster2, Duration::new(0, 0), rules);
+        if jams.is_empty() {
+            jams.push(JamState::default());
+        }
+        game.jams = jams;
+        game.second_period_start = second_period_start;
+        game.timeout_log = timeout_log;
+        game.tostate = ActiveTimeout::None;
+        game.editing = true;
+        game
+    }
+    pub fn editing(&self) -> bool { self.editing }
+    pub fn created(&self) -> u64 { self.created }
+    /// Take on the set-up time of the game this one was imported from, so
+    /// that it's archived as the same game.
+    pub fn set_created(&mut self, created: u64) { self.created = created }
+    pub fn igrf(&self) -> &Igrf { &self.igrf }
+    pub fn final_at(&self) -> Option<u64> { self.final_at }
+    /// Replace the IGRF. Returns false, and leaves it alone, if the game
+    /// has already been marked final.
+    pub fn set_igrf(&mut self, igrf: Igrf) -> bool {
+        if self.final_at.is_some() {
+            return false;
+        }
+        self.igrf = igrf;
+        true
+    }
+    /// Whether the second period has run out and its last jam is over.
+    pub fn game_over(&self) -> bool {
+        let (period, time) = self.clock.get_time();
+        let clocktype = self.clock.get_active_clock().0;
+        period >= 2 && time == Duration::new(0, 0) &&
+            (clocktype == clock::Clocktype::Intermission || clocktype == clock::Clocktype::None)
+    }
+    /// What has to be filled in before the game can be final: the IGRF,
+    /// and the crew who officiated it.
+    pub fn final_missing(&self) -> Vec<Missing> {
+        let mut missing = self.igrf.missing();
+        if self.officials.is_empty() {
+            missing.push(Missing("Officials"));
+        }
+        missing
+    }
+    /// Mark the game final, if it's over (or is an imported game, which
+    /// was over before it got here) and its paperwork is complete.
+    pub fn mark_final(&mut self) -> Result<(), FinalError> {
+        if !self.editing && !self.game_over() {
+            return Err(FinalError::NotOver);
+        }
+        let missing = self.final_missing();
+        if !missing.is_empty() {
+            return Err(FinalError::Incomplete(missing));
+        }
+        if self.final_at.is_none() {
+            self.final_at = Some(events::now_millis());
         }
+        Ok(())
     }
+    pub fn officials(&self) -> &[Official] { &self.officials }
+    pub fn set_officials(&mut self, officials: Vec<Official>) {
+        self.officials = officials;
+    }
+    pub fn actions(&self) -> &[ActionEntry] { &self.actions }
+    /// Put down events taken with `take_events` as the doing of an
+    /// official.
+    pub fn record_actions(&mut self, by: &Operator, events: &[Event]) {
+        let now = events::now_millis();
+        self.actions.extend(events.iter().map(|e| ActionEntry {
+            time: now, by: by.clone(), event: e.clone(),
+        }));
+    }
+    /// Whether any jams have been played.
+    pub fn started(&self) -> bool { self.jams[0].played() }
     pub fn total_score(&self) -> (u32, u32) {
         let mut sums = (0, 0);
         for jam in &self.jams {
@@ -106,18 +299,64 @@ impl GameState {
     }
 
     pub fn start_jam(&mut self) {
-        self.clock.start_jam();
+        self.start_jam_at(Duration::new(0, 0));
+    }
+    /// Start a jam that actually started `lag` ago.
+    pub fn start_jam_at(&mut self, lag: Duration) {
+        if self.clock.get_active_clock().0 == clock::Clocktype::Jam {
+            return;
+        }
+        self.clock.start_jam_at(lag);
         self.tostate = ActiveTimeout::None;
-        self.jams.last_mut().unwrap().starttime = Some(Instant::now());
+        self.jams.last_mut().unwrap().starttime = Some(Instant::now() - lag);
+        let (period, jam) = (self.clock.get_time().0, self.jamnum());
+        self.events.push(Event::JamStarted { period: period, jam: jam });
     }
     pub fn stop_jam(&mut self) {
-        self.clock.stop_jam();
-        self.jams.last_mut().unwrap().endtime = Some(Instant::now());
-        self.jams.push(JamState::default());
+        self.stop_jam_at(Duration::new(0, 0));
+    }
+    /// Stop the jam as of `lag` ago.
+    pub fn stop_jam_at(&mut self, lag: Duration) {
+        self.clock.stop_jam_at(lag);
+        // Only wrap up the current jam if it actually ran, so that calling
+        // a timeout during lineup doesn't create an empty jam.
+        if self.cur_jam().starttime.is_none() || self.cur_jam().endtime.is_some() {
+            return;
+        }
+        self.jams.last_mut().unwrap().endtime = Some(Instant::now() - lag);
+        let ended = Event::JamEnded {
+            period: self.clock.get_time().0, jam: self.jamnum(),
+            score: self.total_score(), jamscore: self.cur_jam().jam_score(),
+        };
+        self.events.push(ended);
+        let mut next = JamState::default();
+        for &team in [Team::Home, Team::Away].iter() {
+            next[team] = self.cur_jam()[team].carried_over();
+        }
+        self.jams.push(next);
     }
     pub fn get_time(&self) -> (u8, Duration) {
         self.clock.get_time()
     }
+    /// The state of the clocks as of right now, rather than as of the last
+    /// tick.
+    pub fn clock_state(&self) -> ClockState {
+        let (ty, active) = self.clock.get_active_clock();
+        let age = millis(self.clock.last_update().elapsed());
+        let game = millis(self.clock.get_time().1);
+        let active = millis(active);
+        let game_running = ty.game_clock_runs();
+        let active_running = ty != clock::Clocktype::None;
+        ClockState {
+            game_ms: if game_running { game.saturating_sub(age) } else { game },
+            active_ms: if !active_running { active }
+                       else if ty.counts_down() { active.saturating_sub(age) }
+                       else { active + age },
+            game_running: game_running,
+            active_running: active_running,
+            active_counts_down: ty.counts_down(),
+        }
+    }
     pub fn get_active_clock(&self) -> ActiveClock {
         let (ty, duration) = self.clock.get_active_clock();
         match ty {
@@ -153,17 +392,29 @@ impl GameState {
         }
     }
 
+    /// Take the events that have happened since the last call.
+    pub fn take_events(&mut self) -> Vec<Event> {
+        mem::replace(&mut self.events, Vec::new())
+    }
+
     pub fn tick(&mut self) -> () {
-        let oldclocktype = self.clock.get_active_clock().0;
+        if self.editing {
+            return;
+        }
+        let (oldclocktype, oldclock) = self.clock.get_active_clock();
         let clock_expired = self.clock.tick();
         if clock_expired {
             let newclocktype = self.clock.get_active_clock().0;
             match oldclocktype {
-                clock::Clocktype::Jam => self.stop_jam(),
+                clock::Clocktype::Jam => {
+                    let (period, jam) = (self.clock.get_time().0, self.jamnum());
+                    self.events.push(Event::JamEndedByTime { period: period, jam: jam });
+                    self.stop_jam();
+                },
                 clock::Clocktype::Intermission => {
                     if let ActiveTimeout::Halftime = self.tostate {
-                        self.team1.reviews = 2;
-                        self.team2.reviews = 2;
+                        self.team1.reviews = self.rules.reviews;
+                        self.team2.reviews = self.rules.reviews;
                     }
                     self.tostate = ActiveTimeout::None;
                     if self.clock.get_time().0 == 2 {
@@ -176,7 +427,9 @@ impl GameState {
             };
             match newclocktype {
                 clock::Clocktype::Intermission => {
-                    if self.clock.get_time().0 == 1 {
+                    let period = self.clock.get_time().0;
+                    self.events.push(Event::PeriodEnded { period: period });
+                    if period == 1 {
                         // period 1 expired, in intermission
                         self.second_period_start = self.jams.len() - 1;
                         self.tostate = ActiveTimeout::Halftime;
@@ -184,28 +437,61 @@ impl GameState {
                         // period 2 expired, game over.
                         // TODO: overtime
                         self.tostate = ActiveTimeout::None;
+                        self.events.push(Event::GameOver);
                     }
                 },
-                clock::Clocktype::Jam | clock::Clocktype::Lineup => {},
+                clock::Clocktype::Jam => {
+                    // Lineup ran out, so the clock started the jam itself.
+                    self.tostate = ActiveTimeout::None;
+                    self.jams.last_mut().unwrap().starttime = Some(Instant::now());
+                    let (period, jam) = (self.clock.get_time().0, self.jamnum());
+                    self.events.push(Event::JamStarted { period: period, jam: jam });
+                },
+                clock::Clocktype::Lineup => {},
                 clock::Clocktype::None => {
                     self.tostate = ActiveTimeout::None;
                 }
+                clock::Clocktype::OtherTimeout => {
+                    // A team timeout that ran too long carries on as an
+                    // official timeout.
+                    self.tostate = ActiveTimeout::Official;
+                }
                 _ => unreachable!()
             };
+        } else {
+            let newclock = self.clock.get_active_clock().1;
+            let crossed = |secs| oldclock < Duration::new(secs, 0) &&
+                                 newclock >= Duration::new(secs, 0);
+            let crossed_down = |secs| oldclock > Duration::new(secs, 0) &&
+                                      newclock <= Duration::new(secs, 0);
+            match oldclocktype {
+                clock::Clocktype::Lineup if crossed_down(5) =>
+                    self.events.push(Event::FiveSecondsToJam),
+                clock::Clocktype::TeamTimeout if crossed(60) =>
+                    self.events.push(Event::TimeoutWarning),
+                _ => (),
+            }
         }
     }
     pub fn jamnum(&self) -> u8 {
-        return (self.jams.len() - self.second_period_start) as u8;
+        let period = if self.second_period_start == 0 { 1 } else { 2 };
+        self.offset_jam(period, self.jams.len() - self.second_period_start)
     }
 
-    fn jamidx_to_periodjam(&self, jamidx: usize) -> (u8, u8) {
-        if self.second_period_start == 0 {
-            (1u8, jamidx as u8 + 1)
+    fn offset_jam(&self, period: u8, jam: usize) -> u8 {
+        let offset = self.jam_offsets[if period >= 2 { 1 } else { 0 }];
+        adjust(jam as u32, offset as i32) as u8
+    }
+
+    pub fn jamidx_to_periodjam(&self, jamidx: usize) -> (u8, u8) {
+        let (period, jam) = if self.second_period_start == 0 {
+            (1u8, jamidx + 1)
         } else if jamidx < self.second_period_start {
-            (1, jamidx as u8 + 1)
+            (1, jamidx + 1)
         } else {
-            (2, (jamidx - self.second_period_start + 1) as u8)
-        }
+            (2, jamidx - self.second_period_start + 1)
+        };
+        (period, self.offset_jam(period, jam))
     }
 
     pub fn team_penalties(&self, team: Team) -> HashMap<String, Vec<Penalty>> {
@@ -227,58 +513,259 @@ impl GameState {
         HashMap::from_iter(z)
     }
 
-    pub fn penalty(&mut self, team: Team, skater: &str, code: char) {
-        let skater_idx = {
-            let team = &self[team].roster;
-            match team.skaters.binary_search_by_key(&skater, |s| &*s.number) {
-                Ok(idx) => idx,
-                Err(_) => { println!("skater {} not found", skater); return }
-            }
+    /// Record a penalty in the `jam`th jam of the game, or the current jam
+    /// if `jam` is `None`.
+    pub fn penalty(&mut self, team: Team, jam: Option<usize>, skater: &str, code: char)
+                   -> Result<(), PenaltyError> {
+        let skater_idx = match self[team].roster.skater_index(skater) {
+            Some(idx) => idx,
+            None => return Err(PenaltyError::UnknownSkater),
         };
-        let jam = self.jams.last_mut().unwrap();
-        let mut penalties = &mut jam[team].penalties;
-        penalties.push((skater_idx, PenaltyType::from_char(code)));
+        let jamidx = match jam {
+            Some(j) if j >= 1 && j <= self.jams.len() => j - 1,
+            Some(_) => return Err(PenaltyError::NoSuchJam),
+            None => self.jams.len() - 1,
+        };
+        if !self.jams[jamidx][team].add_penalty(skater_idx, code) {
+            return Err(PenaltyError::UnknownCode);
+        }
+        let (period, jam) = self.jamidx_to_periodjam(jamidx);
+        self.events.push(Event::Penalty {
+            team: team, period: period, jam: jam, skater: String::from(skater), code: code,
+        });
+        Ok(())
+    }
+    /// Set the points for one of a team's scoring trips in the `jam`th jam
+    /// of the game. Returns false if there is no such jam.
+    /// Note that something other than a trip changed in a team's side of a
+    /// jam, by jam number, after it's been changed directly.
+    pub fn jam_changed(&mut self, jam: usize, team: Team) {
+        let (period, jamnum) = self.jamidx_to_periodjam(jam - 1);
+        self.events.push(Event::JamChanged { team: team, period: period, jam: jamnum });
+    }
+    pub fn scoring_trip(&mut self, jam: usize, team: Team, trip: u8, points: u8) -> bool {
+        match self.get_jam_mut(jam) {
+            Some(j) => j[team].set_score(trip, points),
+            None => return false,
+        }
+        let (period, jamnum) = self.jamidx_to_periodjam(jam - 1);
+        self.events.push(Event::Trip {
+            team: team, period: period, jam: jamnum, trip: trip, points: points,
+        });
+        true
     }
-    pub fn official_timeout(&mut self) -> () {
+    /// Call an official timeout. Returns false, and leaves things alone,
+    /// if there's no game going to stop, e.g. during intermission.
+    pub fn official_timeout(&mut self) -> bool {
         self.stop_jam();
-        self.tostate = ActiveTimeout::Official;
         self.clock.other_timeout();
+        if self.clock.get_active_clock().0 != clock::Clocktype::OtherTimeout {
+            return false;
+        }
+        self.tostate = ActiveTimeout::Official;
+        self.timeout_started(TimeoutKind::Official, None);
+        true
     }
+    /// End the timeout or review and go back to lineup.
+    pub fn end_timeout(&mut self) -> () {
+        match self.clock.get_active_clock().0 {
+            clock::Clocktype::TeamTimeout | clock::Clocktype::OtherTimeout => (),
+            _ => return,
+        }
+        self.clock.end_timeout();
+        self.tostate = ActiveTimeout::None;
+        self.events.push(Event::TimeoutEnded);
+    }
+    /// The allowance a timeout is charged against, if any.
+    fn allowance(&mut self, to: ActiveTimeout) -> Option<&mut u8> {
+        match to {
+            ActiveTimeout::TeamTO(team) => Some(&mut self[team].timeouts),
+            ActiveTimeout::Review(team) => Some(&mut self[team].reviews),
+            _ => None,
+        }
+    }
+    /// Turn the running timeout into another kind, e.g. a team timeout
+    /// into an official review, or attribute an official timeout to a
+    /// team. Whatever the old timeout cost is refunded. Returns false, and
+    /// leaves things alone, if the team has none of the new kind left.
+    pub fn convert_timeout(&mut self, kind: TimeoutKind, team: Option<Team>) -> bool {
+        let old = self.tostate;
+        match old {
+            ActiveTimeout::TeamTO(_) | ActiveTimeout::Official | ActiveTimeout::Review(_) => (),
+            _ => return false,
+        }
+        let new = match (kind, team) {
+            (TimeoutKind::Official, _) => ActiveTimeout::Official,
+            (TimeoutKind::Team, Some(t)) => ActiveTimeout::TeamTO(t),
+            (TimeoutKind::Review, Some(t)) => ActiveTimeout::Review(t),
+            _ => return false,
+        };
+        if let Some(n) = self.allowance(old) { *n += 1 }
+        if self.allowance(new).map_or(false, |n| *n == 0) {
+            if let Some(n) = self.allowance(old) { *n -= 1 }
+            return false;
+        }
+        if let Some(n) = self.allowance(new) { *n -= 1 }
+        self.tostate = new;
+        let ty = match new {
+            ActiveTimeout::TeamTO(_) => clock::Clocktype::TeamTimeout,
+            _ => clock::Clocktype::OtherTimeout,
+        };
+        self.clock.convert_timeout(ty);
+        let team = match kind { TimeoutKind::Official => None, _ => team };
+        if let Some(entry) = self.timeout_log.last_mut() {
+            entry.kind = kind;
+            entry.team = team;
+        }
+        let index = self.timeout_log.len().saturating_sub(1);
+        self.events.push(Event::Timeout { index: index, kind: kind, team: team });
+        true
+    }
+    fn timeout_started(&mut self, kind: TimeoutKind, team: Option<Team>) {
+        let entry = TimeoutEntry {
+            period: self.clock.get_time().0, after_jam: self.jamnum().saturating_sub(1),
+            kind: kind, team: team,
+        };
+        self.timeout_log.push(entry);
+        let index = self.timeout_log.len() - 1;
+        self.events.push(Event::Timeout { index: index, kind: kind, team: team });
+    }
+    /// Call a team timeout, or an official timeout if the team has none
+    /// left. Returns false, and leaves things alone, if there's no game
+    /// going to stop, e.g. during intermission.
     pub fn team_timeout(&mut self, team: Team) -> bool {
+        if self[team].timeouts == 0 {
+            return self.official_timeout();
+        }
         self.stop_jam();
-        let timeout_allowed = self[team].timeouts > 0;
-        if timeout_allowed {
-            self[team].timeouts -=1;
-            self.tostate = ActiveTimeout::TeamTO(team);
-            self.clock.team_timeout();
-        } else {
-            self.tostate = ActiveTimeout::Official;
-            self.clock.other_timeout();
+        self.clock.team_timeout();
+        if self.clock.get_active_clock().0 != clock::Clocktype::TeamTimeout {
+            return false;
         }
-        timeout_allowed
+        self[team].timeouts -= 1;
+        self.tostate = ActiveTimeout::TeamTO(team);
+        self.timeout_started(TimeoutKind::Team, Some(team));
+        true
     }
 
+    /// Call an official review, or an official timeout if the team has
+    /// none left. Returns false, and leaves things alone, if there's no
+    /// game going to stop.
     pub fn official_review(&mut self, team: Team) -> bool {
-        self.stop_jam();
-        let review_allowed = self[team].reviews > 0;
-        if review_allowed {
-            self[team].reviews -= 1;
-            self.tostate = ActiveTimeout::Review(team);
-        } else {
-            self.tostate = ActiveTimeout::Official;
+        if self[team].reviews == 0 {
+            return self.official_timeout();
         }
+        self.stop_jam();
         self.clock.other_timeout();
-        review_allowed
+        if self.clock.get_active_clock().0 != clock::Clocktype::OtherTimeout {
+            return false;
+        }
+        self[team].reviews -= 1;
+        self.tostate = ActiveTimeout::Review(team);
+        self.timeout_started(TimeoutKind::Review, Some(team));
+        true
+    }
+    fn record_correction(&mut self, correction: Correction) {
+        let entry = CorrectionEntry {
+            time: events::now_millis(), period: self.clock.get_time().0,
+            jam: self.jamnum(), correction: correction.clone(),
+        };
+        self.corrections.push(entry);
+        self.events.push(Event::Correction { correction: correction });
     }
     pub fn set_time(&mut self, time: Duration) {
+        let from = millis(self.clock.get_time().1);
         self.clock.set_time(time);
+        self.record_correction(Correction::PeriodClock { from: from, to: millis(time) });
+    }
+    pub fn set_active_time(&mut self, time: Duration) {
+        let from = millis(self.clock.get_active_clock().1);
+        self.clock.set_active_time(time);
+        self.record_correction(Correction::ActiveClock { from: from, to: millis(time) });
+    }
+    pub fn set_period(&mut self, period: u8) {
+        let from = self.clock.get_time().0;
+        if period == from {
+            return;
+        }
+        self.clock.set_period(period);
+        // Keep the jams of the new period numbered from its first jam,
+        // which is where it started before if it's being put back.
+        if period >= 2 && from < 2 {
+            let last = self.jams.len() - 1;
+            self.second_period_start = match self.earlier_second_period_start.take() {
+                Some(start) if start <= last => start,
+                _ => last,
+            };
+        } else if period < 2 && self.second_period_start != 0 {
+            self.earlier_second_period_start = Some(self.second_period_start);
+            self.second_period_start = 0;
+        }
+        self.record_correction(Correction::Period { from: from, to: period });
+    }
+    pub fn set_jamnum(&mut self, jam: u8) {
+        let from = self.jamnum();
+        let idx = if self.second_period_start == 0 { 0 } else { 1 };
+        self.jam_offsets[idx] += jam as i16 - from as i16;
+        self.record_correction(Correction::Jam { from: from, to: jam });
+    }
+    pub fn set_timeouts(&mut self, team: Team, timeouts: u8) {
+        let from = self[team].timeouts;
+        self[team].timeouts = timeouts;
+        self.record_correction(Correction::Timeouts { team: team, from: from, to: timeouts });
+    }
+    pub fn set_reviews(&mut self, team: Team, reviews: u8) {
+        let from = self[team].reviews;
+        self[team].reviews = reviews;
+        self.record_correction(Correction::Reviews { team: team, from: from, to: reviews });
+    }
+    /// Set a team's total score. The trips are left alone, and the
+    /// difference is kept as a correction to the last played jam's points,
+    /// or to earlier jams' as well if taking it all off that jam would
+    /// leave it below zero. The jam being lined up for only gets it if
+    /// no jam has been played yet.
+    pub fn set_score(&mut self, team: Team, score: u32) {
+        let from = match team {
+            Team::Home => self.total_score().0,
+            Team::Away => self.total_score().1,
+        };
+        let mut diff = score as i32 - from as i32;
+        for jam in self.jams.iter_mut().rev().filter(|j| j.played()) {
+            if diff == 0 {
+                break;
+            }
+            let tj = &mut jam[team];
+            let change = ::std::cmp::max(diff, -(tj.points() as i32));
+            let adjust = tj.score_adjust() + change;
+            tj.set_score_adjust(adjust);
+            diff -= change;
+        }
+        if diff > 0 {
+            let tj = &mut self.jams.last_mut().unwrap()[team];
+            let adjust = tj.score_adjust() + diff;
+            tj.set_score_adjust(adjust);
+        }
+        self.record_correction(Correction::Score { team: team, from: from, to: score });
+    }
+    pub fn timeout_log(&self) -> &[TimeoutEntry] { &self.timeout_log }
+    pub fn corrections(&self) -> &[CorrectionEntry] {
+        &self.corrections
     }
     pub fn review_lost(&mut self, team: Team) {
         self[team].reviews = 0;
+        self.events.push(Event::ReviewOutcome { team: team, retained: false });
+    }
+    pub fn review_retained(&mut self, team: Team) {
+        self.events.push(Event::ReviewOutcome { team: team, retained: true });
     }
     pub fn roster(&self, team: Team) -> &roster::Team {
         &self[team].roster
     }
+    /// Override a team's colour for this game only, e.g. for alternate
+    /// jerseys. `None` clears the colour.
+    pub fn set_team_color(&mut self, team: Team, color: Option<String>) {
+        self[team].roster.color = color;
+    }
     pub fn timeouts(&self) -> (u8, u8) {
         (self[Team::Home].timeouts, self[Team::Away].timeouts)
     }
@@ -295,8 +782,150 @@ impl GameState {
     pub fn cur_jam_mut(&mut self) -> &mut JamState {
         self.jams.last_mut().unwrap()
     }
-    pub fn get_jam(&self, i: usize) -> &JamState { &self.jams[i - 1] }
-    pub fn get_jam_mut(&mut self, i: usize) -> &mut JamState { &mut self.jams[i - 1] }
+    /// Jams are numbered from 1 over the whole game.
+    pub fn get_jam(&self, i: usize) -> Option<&JamState> {
+        i.checked_sub(1).and_then(move |i| self.jams.get(i))
+    }
+    pub fn get_jam_mut(&mut self, i: usize) -> Option<&mut JamState> {
+        match i.checked_sub(1) {
+            Some(i) => self.jams.get_mut(i),
+            None => None,
+        }
+    }
+    pub fn rules(&self) -> &Ruleset { &self.rules }
     pub fn jams(&self) -> &[JamState] { self.jams.as_ref() }
+    /// Redo what a finished jam, by jam number, carries over into the next
+    /// one for a team, after its lineup or box trips have changed.
+    pub fn carry_over(&mut self, jam: usize, team: Team) {
+        if jam == 0 || jam >= self.jams.len() {
+            return;
+        }
+        let carried = self.jams[jam - 1][team].carried_over();
+        self.jams[jam][team].take_carried(&carried);
+        self.jam_changed(jam + 1, team);
+    }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use roster;
+    use gamestate::{FinalError, GameState};
+    use gamestate::jamstate::{BoxMark, Team};
+    use gamestate::ruleset::Ruleset;
+
+    fn new_game() -> GameState {
+        GameState::new(roster::Team::new(String::from("Home")),
+                       roster::Team::new(String::from("Away")),
+                       Duration::new(0, 0), Ruleset::default())
+    }
+
+    fn play_jam(game: &mut GameState, home: i8, away: i8) {
+        game.start_jam();
+        game.cur_jam_mut().adj_score(home, away);
+        game.stop_jam();
+    }
+
+    #[test]
+    fn test_score_correction() {
+        let mut game = new_game();
+        play_jam(&mut game, 4, 0);
+        play_jam(&mut game, 2, 3);
+        game.set_score(Team::Home, 10);
+        assert_eq!(game.total_score(), (10, 3));
+        assert_eq!(game.jams()[1].jam_score(), (6, 3));
+        assert_eq!(game.jams()[1][Team::Home].trips(), vec![2]);
+        // Not the jam being lined up for.
+        assert_eq!(game.jams()[2].jam_score(), (0, 0));
+
+        // Taking off more than the jam has reaches back to earlier jams.
+        game.set_score(Team::Home, 1);
+        assert_eq!(game.total_score(), (1, 3));
+        assert_eq!(game.jams()[1].jam_score(), (0, 3));
+        assert_eq!(game.jams()[0].jam_score(), (1, 0));
+        assert_eq!(game.corrections().len(), 2);
+    }
+
+    #[test]
+    fn test_period_correction() {
+        let mut game = new_game();
+        play_jam(&mut game, 0, 0);
+        play_jam(&mut game, 0, 0);
+        game.set_period(2);
+        assert_eq!(game.jamnum(), 1);
+        assert_eq!(game.jamidx_to_periodjam(1), (1, 2));
+
+        game.set_period(1);
+        assert_eq!(game.jamnum(), 3);
+        play_jam(&mut game, 0, 0);
+        // Moving on again puts the boundary back where it was.
+        game.set_period(2);
+        assert_eq!(game.jamidx_to_periodjam(2), (2, 1));
+        assert_eq!(game.jamnum(), 2);
+    }
+
+    #[test]
+    fn test_jam_correction() {
+        let mut game = new_game();
+        play_jam(&mut game, 0, 0);
+        game.set_jamnum(5);
+        assert_eq!(game.jamnum(), 5);
+        assert_eq!(game.jamidx_to_periodjam(0), (1, 4));
+        play_jam(&mut game, 0, 0);
+        assert_eq!(game.jamnum(), 6);
+    }
+
+    #[test]
+    fn test_timeout_without_game() {
+        let mut game = new_game();
+        assert!(!game.team_timeout(Team::Home));
+        assert!(!game.official_review(Team::Away));
+        assert_eq!(game[Team::Home].timeouts, game.rules().timeouts);
+        assert_eq!(game[Team::Away].reviews, game.rules().reviews);
+        assert!(game.timeout_log().is_empty());
+
+        play_jam(&mut game, 0, 0);
+        assert!(game.team_timeout(Team::Home));
+        assert_eq!(game[Team::Home].timeouts, game.rules().timeouts - 1);
+        assert_eq!(game.timeout_log().len(), 1);
+    }
+
+    #[test]
+    fn test_mark_final() {
+        let mut game = new_game();
+        play_jam(&mut game, 0, 0);
+        match game.mark_final() {
+            Err(FinalError::NotOver) => (),
+            _ => panic!("a game in progress was marked final"),
+        }
+
+        let mut game = GameState::imported(roster::Team::new(String::from("Home")),
+                                           roster::Team::new(String::from("Away")),
+                                           Ruleset::default(), Vec::new(), 0, Vec::new());
+        match game.mark_final() {
+            Err(FinalError::Incomplete(missing)) =>
+                assert!(missing.iter().any(|m| m.0 == "Officials")),
+            _ => panic!("an incomplete game was marked final"),
+        }
+        assert!(game.final_at().is_none());
+    }
+
+    #[test]
+    fn test_carry_over_correction() {
+        let mut game = new_game();
+        game.start_jam();
+        game.cur_jam_mut()[Team::Home].set_lineup_slot(3, Some(4));
+        game.stop_jam();
+        assert_eq!(game.jams()[1][Team::Home].lineup_slot(3), None);
+
+        game.get_jam_mut(1).unwrap()[Team::Home].set_box_trips(3, vec![BoxMark::AfterJam]);
+        game.carry_over(1, Team::Home);
+        assert_eq!(game.jams()[1][Team::Home].lineup_slot(3), Some(4));
+        assert_eq!(game.jams()[1][Team::Home].box_trips(3), &[BoxMark::Started]);
+
+        // The jam in progress has nothing after it to carry over into.
+        game.carry_over(2, Team::Home);
+        assert_eq!(game.jams().len(), 2);
+    }
+}
@@ -56,7 +56,7 @@ impl PenaltyType {
             PenaltyType::CutTrack => 'X',
             PenaltyType::IllegalProcedure => 'I',
             PenaltyType::OutOfPlay => 'P',
-            PenaltyType::OutOfBounds => 'B',
+            PenaltyType::OutOfBounds => 'O',
             PenaltyType::SkatingOutOfBounds => 'S',
             PenaltyType::Insubordination => 'N',
             PenaltyType::Misconduct => 'G',
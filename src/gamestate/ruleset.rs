@@ -0,0 +1,56 @@
+/*! Rulesets: the clock lengths and timeout allowances that vary between
+    kinds of game.
+ */
+
+use std::time::Duration;
+
+#[derive(Clone, Copy, Serialize)]
+pub struct Ruleset {
+    pub period_length: Duration,
+    pub jam_length: Duration,
+    pub lineup_length: Duration,
+    pub intermission_length: Duration,
+    /// Team timeouts per game.
+    pub timeouts: u8,
+    /// Official reviews per period.
+    pub reviews: u8,
+    /// Penalties that foul a skater out of the game.
+    pub foul_out: u8,
+}
+
+pub const RULESET_NAMES: [&'static str; 2] = ["wftda", "short"];
+
+impl Ruleset {
+    pub fn wftda() -> Ruleset {
+        Ruleset {
+            period_length: Duration::new(30 * 60, 0),
+            jam_length: Duration::new(120, 0),
+            lineup_length: Duration::new(30, 0),
+            intermission_length: Duration::new(10 * 60, 0),
+            timeouts: 3,
+            reviews: 2,
+            foul_out: 7,
+        }
+    }
+
+    /// Shorter periods for scrimmages and tournament play.
+    pub fn short() -> Ruleset {
+        Ruleset {
+            period_length: Duration::new(15 * 60, 0),
+            intermission_length: Duration::new(5 * 60, 0),
+            ..Ruleset::wftda()
+        }
+    }
+
+    pub fn by_name(name: &str) -> Option<Ruleset> {
+        match name {
+            "wftda" => Some(Ruleset::wftda()),
+            "short" => Some(Ruleset::short()),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Ruleset {
+    fn default() -> Ruleset { Ruleset::wftda() }
+}
@@ -0,0 +1,279 @@
+/*! Game statistics. These are computed on demand from a `GameState`, so
+    they always reflect the current state of the score sheet, penalties
+    and lineups, including any after-the-fact corrections.
+ */
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use super::GameState;
+use super::jamstate::{JamState, Team};
+
+#[derive(Serialize, Default)]
+pub struct TeamStats {
+    pub name: String,
+    pub jams: u32,
+    pub lead_jams: u32,
+    pub lead_pct: f32,
+    pub points: u32,
+    pub points_per_jam: f32,
+    pub points_against: u32,
+    pub penalties_by_period: Vec<u32>,
+    pub penalties_by_code: BTreeMap<String, u32>,
+    pub skaters: Vec<SkaterStats>,
+}
+
+#[derive(Serialize, Default)]
+pub struct SkaterStats {
+    pub number: String,
+    pub name: String,
+    pub jammer_jams: u32,
+    pub lead_jams: u32,
+    pub jammer_points_for: u32,
+    pub jammer_points_against: u32,
+    pub blocker_jams: u32,
+    pub plus_minus: i32,
+    pub penalties: u32,
+}
+
+#[derive(Serialize)]
+pub struct GameStats {
+    pub jams: u32,
+    /// Average length of a completed jam, in seconds, leaving out jams
+    /// with no times. `None` if no jam has times, as in an imported game.
+    pub average_jam_length: Option<f32>,
+    pub teams: Vec<TeamStats>,
+}
+
+fn other(team: Team) -> Team {
+    match team {
+        Team::Home => Team::Away,
+        Team::Away => Team::Home,
+    }
+}
+
+fn jam_points(jam: &JamState, team: Team) -> u32 {
+    let score = jam.jam_score();
+    match team {
+        Team::Home => score.0,
+        Team::Away => score.1,
+    }
+}
+
+fn ratio(num: u32, denom: u32) -> f32 {
+    if denom == 0 { 0.0 } else { num as f32 / denom as f32 }
+}
+
+fn as_secs_f32(d: Duration) -> f32 {
+    d.as_secs() as f32 + d.subsec_nanos() as f32 / 1e9
+}
+
+impl GameState {
+    /// Jams that have actually been played.
+    fn played_jams(&self) -> Vec<&JamState> {
+        self.jams.iter().filter(|jam| jam.played()).collect()
+    }
+
+    fn team_stats(&self, team: Team) -> TeamStats {
+        let periods = self.jamidx_to_periodjam(self.jams.len() - 1).0 as usize;
+        let mut stats = TeamStats {
+            name: self[team].roster.name.clone(),
+            penalties_by_period: vec![0; periods],
+            skaters: self.skater_stats(team),
+            ..TeamStats::default()
+        };
+        for jam in self.played_jams() {
+            stats.jams += 1;
+            if jam[team].lead() { stats.lead_jams += 1 }
+            stats.points += jam_points(jam, team);
+            stats.points_against += jam_points(jam, other(team));
+        }
+        for (idx, jam) in self.jams.iter().enumerate() {
+            let (period, _) = self.jamidx_to_periodjam(idx);
+            for &(_, code) in &jam[team].penalties {
+                stats.penalties_by_period[period as usize - 1] += 1;
+                *stats.penalties_by_code.entry(code.as_char().to_string())
+                    .or_insert(0) += 1;
+            }
+        }
+        stats.lead_pct = 100.0 * ratio(stats.lead_jams, stats.jams);
+        stats.points_per_jam = ratio(stats.points, stats.jams);
+        stats
+    }
+
+    fn skater_stats(&self, team: Team) -> Vec<SkaterStats> {
+        let mut stats = self[team].roster.skaters.iter().map(|s| SkaterStats {
+            number: s.number.clone(),
+            name: s.name.clone(),
+            ..SkaterStats::default()
+        }).collect::<Vec<_>>();
+
+        for jam in self.played_jams() {
+            let teamjam = &jam[team];
+            let against = jam_points(jam, other(team));
+            let diff = jam_points(jam, team) as i32 - against as i32;
+            if let Some(s) = teamjam.jammer().and_then(|i| stats.get_mut(i)) {
+                s.jammer_jams += 1;
+                if teamjam.lead() { s.lead_jams += 1 }
+                s.jammer_points_for += teamjam.jammer_points();
+                s.jammer_points_against += against;
+            }
+            if teamjam.starpass() {
+                if let Some(s) = teamjam.pivot().and_then(|i| stats.get_mut(i)) {
+                    s.jammer_points_for += teamjam.pivot_points();
+                }
+            }
+            for idx in teamjam.blockers() {
+                if let Some(s) = stats.get_mut(idx) {
+                    s.blocker_jams += 1;
+                    s.plus_minus += diff;
+                }
+            }
+        }
+        for jam in &self.jams {
+            for &(idx, _) in &jam[team].penalties {
+                if let Some(s) = stats.get_mut(idx) {
+                    s.penalties += 1;
+                }
+            }
+        }
+        stats
+    }
+
+    pub fn stats(&self) -> GameStats {
+        let played = self.played_jams();
+        let lengths = played.iter().filter_map(|jam| {
+            match (jam.starttime, jam.endtime) {
+                (Some(start), Some(end)) => Some(as_secs_f32(end - start)),
+                _ => None,
+            }
+        }).collect::<Vec<f32>>();
+        let total_length = lengths.iter().fold(0.0, |acc, l| acc + l);
+        let average = if lengths.is_empty() { None }
+                      else { Some(total_length / lengths.len() as f32) };
+
+        GameStats {
+            jams: played.len() as u32,
+            average_jam_length: average,
+            teams: vec![self.team_stats(Team::Home), self.team_stats(Team::Away)],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use roster;
+    use gamestate::GameState;
+    use gamestate::jamstate::{JamState, Team};
+    use gamestate::penaltycodes::PenaltyType;
+    use gamestate::ruleset::Ruleset;
+
+    fn empty_team(name: &str) -> roster::Team {
+        roster::Team::new(String::from(name))
+    }
+
+    fn team(name: &str, numbers: &[&str]) -> roster::Team {
+        let mut team = empty_team(name);
+        team.skaters = numbers.iter().map(|n| roster::Skater {
+            number: String::from(*n), name: format!("Skater {}", n),
+        }).collect();
+        team
+    }
+
+    #[test]
+    fn test_team_stats() {
+        let mut game = GameState::new(empty_team("Home"), empty_team("Away"),
+                                      Duration::new(0, 0), Ruleset::default());
+        game.start_jam();
+        game.cur_jam_mut()[Team::Home].set_lead(true);
+        game.cur_jam_mut().adj_score(4, 0);
+        game.stop_jam();
+        game.start_jam();
+        game.cur_jam_mut().adj_score(0, 2);
+        game.stop_jam();
+
+        let stats = game.stats();
+        assert_eq!(stats.jams, 2);
+        assert_eq!(stats.teams[0].lead_jams, 1);
+        assert_eq!(stats.teams[0].lead_pct, 50.0);
+        assert_eq!(stats.teams[0].points, 4);
+        assert_eq!(stats.teams[1].points_per_jam, 1.0);
+        assert_eq!(stats.teams[1].points_against, 4);
+    }
+
+    #[test]
+    fn test_skater_stats() {
+        let mut game = GameState::new(team("Home", &["1", "2", "3", "4", "5"]),
+                                      empty_team("Away"), Duration::new(0, 0),
+                                      Ruleset::default());
+        game.start_jam();
+        {
+            let tj = &mut game.cur_jam_mut()[Team::Home];
+            tj.set_lineup_slot(0, Some(0));
+            tj.set_lineup_slot(1, Some(1));
+            tj.set_lineup_slot(2, Some(2));
+            tj.set_lead(true);
+            tj.set_score(2, 4);
+        }
+        game.cur_jam_mut().adj_score(0, 1);
+        game.stop_jam();
+
+        let skaters = game.stats().teams.remove(0).skaters;
+        assert_eq!(skaters[0].jammer_jams, 1);
+        assert_eq!(skaters[0].lead_jams, 1);
+        assert_eq!(skaters[0].jammer_points_for, 4);
+        assert_eq!(skaters[0].jammer_points_against, 1);
+        // The pivot isn't counted as a blocker.
+        assert_eq!(skaters[1].blocker_jams, 0);
+        assert_eq!(skaters[2].blocker_jams, 1);
+        assert_eq!(skaters[2].plus_minus, 3);
+    }
+
+    #[test]
+    fn test_no_pivot_blocker() {
+        let mut game = GameState::new(team("Home", &["1", "2"]), empty_team("Away"),
+                                      Duration::new(0, 0), Ruleset::default());
+        game.start_jam();
+        {
+            let tj = &mut game.cur_jam_mut()[Team::Home];
+            tj.set_lineup_slot(1, Some(1));
+            tj.set_no_pivot(true);
+        }
+        game.stop_jam();
+        assert_eq!(game.stats().teams[0].skaters[1].blocker_jams, 1);
+    }
+
+    #[test]
+    fn test_penalties_by_period() {
+        let mut game = GameState::new(team("Home", &["1"]), empty_team("Away"),
+                                      Duration::new(0, 0), Ruleset::default());
+        game.start_jam();
+        game.cur_jam_mut()[Team::Home].penalties.push((0, PenaltyType::from_char('X')));
+        game.stop_jam();
+        assert_eq!(game.stats().teams[0].penalties_by_period, vec![1]);
+
+        game.second_period_start = 1;
+        game.start_jam();
+        game.cur_jam_mut()[Team::Home].penalties.push((0, PenaltyType::from_char('X')));
+        game.cur_jam_mut()[Team::Home].penalties.push((0, PenaltyType::from_char('C')));
+        game.stop_jam();
+        let stats = game.stats();
+        assert_eq!(stats.teams[0].penalties_by_period, vec![1, 2]);
+        assert_eq!(stats.teams[0].penalties_by_code["X"], 2);
+        assert_eq!(stats.teams[0].skaters[0].penalties, 3);
+    }
+
+    #[test]
+    fn test_untimed_jams() {
+        let mut jam = JamState::default();
+        jam.untimed = true;
+        jam.team1.set_score(2, 4);
+        let game = GameState::imported(empty_team("Home"), empty_team("Away"),
+                                       Ruleset::default(), vec![jam], 0, Vec::new());
+        let stats = game.stats();
+        assert_eq!(stats.jams, 1);
+        assert_eq!(stats.average_jam_length, None);
+        assert_eq!(stats.teams[0].points, 4);
+    }
+}
@@ -9,7 +9,7 @@ fn (foo: usize, game: Game) -> &'static str {
 }
 ```
 These request guards guarantee the existence of a current game: if there
-is no current game, the request will fail.
+is no current game, the request will fail with 409 Conflict.
 Both of the guard types `Game` and `MutGame` implement `Deref` and the latter
 implements `DerefMut` to `GameState`, so you can use them just like you would
 a regular `GameState`.
@@ -18,14 +18,19 @@ a regular `GameState`.
 use std::ops::{Deref, DerefMut};
 use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use rocket;
 use rocket::Outcome;
 use rocket::http::Status;
 use rocket::request::{Request,FromRequest};
 
+use archive;
+use config;
+use events;
 use gamestate;
+use metrics;
+use officials;
 use roster;
 
 /// A request guard for using the current game state, for read-only access.
@@ -42,59 +47,132 @@ impl<'a, 'r> FromRequest<'a, 'r> for Game<'r> {
         // TODO: authentication goes here
         let game = get_game();
         if game.is_none() {
-            Outcome::Failure((Status::BadRequest, ()))
+            Outcome::Failure((Status::Conflict, ()))
         } else {
             Outcome::Success(Game { game: game })
         }
     }
 }
 
-/// A request guard for using the current game state, for read-only access.
-pub struct MutGame<'a> { game: RwLockWriteGuard<'a, Option<gamestate::GameState>> }
+/// A request guard for using the current game state, for read-write access.
+pub struct MutGame<'a> {
+    /// Only `None` once the lock has been let go of, while dropping.
+    game: Option<RwLockWriteGuard<'a, Option<gamestate::GameState>>>,
+    /// The score when the lock was taken, so we can tell if it changed.
+    score: (u32, u32),
+    /// The official making the request, if they've opened a session.
+    operator: Option<officials::Operator>,
+}
 
 impl<'a> Deref for MutGame<'a> {
     type Target = gamestate::GameState;
-    fn deref(&self) -> &gamestate::GameState { &self.game.as_ref().unwrap() }
+    fn deref(&self) -> &gamestate::GameState {
+        self.game.as_ref().unwrap().as_ref().unwrap()
+    }
 }
 
 impl<'a> DerefMut for MutGame<'a> {
-    fn deref_mut(&mut self) -> &mut gamestate::GameState { self.game.as_mut().unwrap() }
+    fn deref_mut(&mut self) -> &mut gamestate::GameState {
+        self.game.as_mut().unwrap().as_mut().unwrap()
+    }
+}
+
+/// Publish whatever happened while the game was locked for writing, and
+/// record it against the official who asked for it. The lock is let go of
+/// first, so subscribers can look at the game straight away.
+impl<'a> Drop for MutGame<'a> {
+    fn drop(&mut self) {
+        let mut happened = Vec::new();
+        if let Some(ref mut game) = **self.game.as_mut().unwrap() {
+            let score = game.total_score();
+            if score != self.score {
+                happened.push(events::Event::ScoreChanged { score: score });
+            }
+            happened.extend(game.take_events());
+            if let Some(ref operator) = self.operator {
+                game.record_actions(operator, &happened);
+            }
+        }
+        self.game = None;
+        publish_events(happened);
+    }
+}
+
+fn publish_events(happened: Vec<events::Event>) {
+    for event in happened {
+        events::publish(event);
+    }
 }
 
 impl<'a, 'r> FromRequest<'a, 'r> for MutGame<'r> {
     type Error = ();
-    fn from_request(_: &'a Request<'r>) -> rocket::request::Outcome<MutGame<'r>, ()> {
+    fn from_request(request: &'a Request<'r>) -> rocket::request::Outcome<MutGame<'r>, ()> {
         // TODO: authentication goes here
+        let operator = request.headers().get_one(officials::SESSION_HEADER)
+            .and_then(officials::operator);
         let game = get_game_mut();
         if game.is_none() {
-            return rocket::Outcome::Failure((Status::BadRequest, ()))
+            return rocket::Outcome::Failure((Status::Conflict, ()))
         }
-        rocket::Outcome::Success(MutGame { game: game })
+        let score = game.as_ref().unwrap().total_score();
+        rocket::Outcome::Success(MutGame { game: Some(game), score: score, operator: operator })
     }
 }
 
 /// Start a new game, with the given rosters and time to derby
 pub fn start_game(team1: roster::Team, team2: roster::Team, time_to_derby: Duration) -> () {
-    *CUR_GAME.write().unwrap() = Some(gamestate::GameState::new(team1, team2, time_to_derby));
+    let rules = config::get().ruleset;
+    set_game(gamestate::GameState::new(team1, team2, time_to_derby, rules));
     thread::spawn(move || {
+        let interval = Duration::new(0, 100_000_000);
+        let mut last = Instant::now();
         loop {
-            thread::park_timeout(Duration::new(0, 100_000_000));
-            let mut guard = get_game_mut();
-            guard.as_mut().unwrap().tick();
+            thread::park_timeout(interval);
+            let now = Instant::now();
+            metrics::record_tick(now - last, interval);
+            last = now;
+            let happened = {
+                let mut guard = get_game_mut();
+                let game = guard.as_mut().unwrap();
+                game.tick();
+                game.take_events()
+            };
+            publish_events(happened);
         }
     });
 }
 
+/// Replace the current game, archiving the old one. This doesn't start a
+/// clock ticking, so use `start_game` for a game that's going to be played.
+pub fn set_game(game: gamestate::GameState) {
+    // The old game is written to the archive once the lock is let go, so
+    // that the clocks and everybody else aren't kept waiting for the disk.
+    let old = {
+        let mut cur = CUR_GAME.write().unwrap();
+        let old = cur.as_ref().and_then(archive::snapshot);
+        *cur = Some(game);
+        old
+    };
+    archive::save_or_log(old);
+    officials::close_all_sessions();
+}
+
 /// Get the current game state for read-only access, in the form of an `RwLockReadGuard`.
 /// You probably want to use the Game struct via rocket's FromRequest mechanism.
 pub fn get_game<'a>() -> RwLockReadGuard<'a, Option<gamestate::GameState>> {
-    CUR_GAME.read().unwrap()
+    let start = Instant::now();
+    let guard = CUR_GAME.read().unwrap();
+    metrics::record_lock_wait("read", start.elapsed());
+    guard
 }
 
 /// Get the current game state for read-write access, in the form of an `RwLockWriteGuard`.
 /// You probably want to use the MutGame struct via rocket's FromRequest mechanism.
 pub fn get_game_mut<'a>() -> RwLockWriteGuard<'a, Option<gamestate::GameState>> {
-    CUR_GAME.write().unwrap()
+    let start = Instant::now();
+    let guard = CUR_GAME.write().unwrap();
+    metrics::record_lock_wait("write", start.elapsed());
+    guard
 }
 
 lazy_static! {
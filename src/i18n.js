@@ -0,0 +1,90 @@
+"use strict";
+
+/* Page labels in the device's language. A `?lang=xx` in the URL, or a
+   choice from a language menu, is remembered in local storage, so each
+   device only needs to be set once. The English text of each label is its
+   key, so anything without a translation stays in English. */
+var I18n = {
+    lang: 'en',
+    strings: {},
+
+    /* Translate a label, filling in {0}, {1}... from the other arguments. */
+    t: function (key) {
+        var s = this.strings.hasOwnProperty(key) ? this.strings[key] : key;
+        var args = arguments;
+        return s.replace(/\{(\d+)\}/g, function (m, i) {
+            var arg = args[parseInt(i) + 1];
+            return arg === undefined ? m : arg;
+        });
+    },
+
+    /* Translate every element with a data-i18n attribute, which holds the
+       English label. data-i18n-arg fills in a {0}. */
+    apply: function () {
+        var els = document.querySelectorAll('[data-i18n]');
+        for (var i = 0; i < els.length; i++) {
+            els[i].textContent = this.t(els[i].dataset.i18n, els[i].dataset.i18nArg);
+        }
+    },
+
+    load: function () {
+        var self = this;
+        var xhr = new XMLHttpRequest();
+        xhr.open('GET', '/i18n/' + encodeURIComponent(this.lang));
+        xhr.onreadystatechange = function () {
+            if (xhr.readyState === XMLHttpRequest.DONE && xhr.status === 200) {
+                self.strings = JSON.parse(xhr.responseText);
+                self.apply();
+            }
+        };
+        xhr.send();
+    },
+
+    setLanguage: function (lang) {
+        try { localStorage.setItem('lang', lang); } catch (e) {}
+        this.lang = lang;
+        this.load();
+    },
+
+    /* Fill in any <select class="lang-select"> with the languages the
+       server knows. */
+    fillMenus: function () {
+        var self = this;
+        var menus = document.querySelectorAll('select.lang-select');
+        if (menus.length === 0) return;
+        var xhr = new XMLHttpRequest();
+        xhr.open('GET', '/i18n');
+        xhr.onreadystatechange = function () {
+            if (xhr.readyState !== XMLHttpRequest.DONE || xhr.status !== 200) return;
+            var langs = JSON.parse(xhr.responseText);
+            for (var i = 0; i < menus.length; i++) {
+                menus[i].innerHTML = '';
+                langs.forEach(function (l) {
+                    var opt = document.createElement('option');
+                    opt.value = l.code;
+                    opt.textContent = l.name;
+                    opt.selected = (l.code === self.lang);
+                    menus[i].appendChild(opt);
+                });
+                menus[i].addEventListener('change', function () {
+                    self.setLanguage(this.value);
+                });
+            }
+        };
+        xhr.send();
+    },
+
+    init: function () {
+        var param = /[?&]lang=([\w-]+)/.exec(window.location.search);
+        var lang = param ? param[1] : null;
+        try {
+            if (lang) localStorage.setItem('lang', lang);
+            lang = localStorage.getItem('lang');
+        } catch (e) {}
+        this.lang = lang || 'en';
+        this.fillMenus();
+        if (this.lang !== 'en') this.load();
+    },
+};
+
+I18n.init();
@@ -0,0 +1,107 @@
+/*! Translations for the page labels. Pages use the English text of each
+    label as its key, and fetch the translations for the device's language
+    from `/i18n/<lang>`, so anything without a translation stays in
+    English.
+
+    A few languages are built in. More can be added, or the built-in ones
+    changed, by putting `<lang>.json` files in the `locales` subdirectory
+    of the data directory, like `{"name": "Español", "strings": {"Lineup":
+    "Alineación"}}`.
+ */
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::path::PathBuf;
+
+use rocket_contrib::Json;
+use serde_json;
+
+use config;
+use error::{ApiError, ApiResult};
+
+const BUILTIN_LOCALES: [(&'static str, &'static str); 3] = [
+    ("de", include_str!("locales/de.json")),
+    ("es", include_str!("locales/es.json")),
+    ("fr", include_str!("locales/fr.json")),
+];
+
+#[derive(Deserialize)]
+struct Locale {
+    name: String,
+    strings: HashMap<String, String>,
+}
+
+#[derive(Serialize)]
+struct LocaleInfo {
+    code: String,
+    name: String,
+}
+
+fn locales_dir() -> PathBuf {
+    config::get().data_dir.join("locales")
+}
+
+fn valid_code(code: &str) -> bool {
+    !code.is_empty() && code.len() <= 16 &&
+        code.chars().all(|c| (c.is_alphanumeric() && (c as u32) < 128) || c == '-' || c == '_')
+}
+
+fn load_file(path: PathBuf) -> Option<Locale> {
+    let file = match File::open(&path) {
+        Ok(f) => f,
+        Err(_) => return None,
+    };
+    match serde_json::from_reader(file) {
+        Ok(locale) => Some(locale),
+        Err(e) => {
+            println!("Ignoring bad locale {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+fn load_locale(code: &str) -> Option<Locale> {
+    if let Some(locale) = load_file(locales_dir().join(format!("{}.json", code))) {
+        return Some(locale);
+    }
+    BUILTIN_LOCALES.iter().find(|&&(c, _)| c == code)
+        .map(|&(_, json)| serde_json::from_str(json).unwrap())
+}
+
+#[get("/i18n")]
+fn list_locales() -> Json<Vec<LocaleInfo>> {
+    let mut names = BTreeMap::new();
+    names.insert(String::from("en"), String::from("English"));
+    for &(code, json) in BUILTIN_LOCALES.iter() {
+        let locale: Locale = serde_json::from_str(json).unwrap();
+        names.insert(String::from(code), locale.name);
+    }
+    if let Ok(entries) = fs::read_dir(locales_dir()) {
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            let code = match path.file_stem().and_then(|s| s.to_str()) {
+                Some(c) if valid_code(c) => String::from(c),
+                _ => continue,
+            };
+            if path.extension().map_or(true, |e| e != "json") {
+                continue;
+            }
+            if let Some(locale) = load_file(path) {
+                names.insert(code, locale.name);
+            }
+        }
+    }
+    Json(names.into_iter().map(|(code, name)| LocaleInfo { code: code, name: name }).collect())
+}
+
+#[get("/i18n/<code>")]
+fn get_locale(code: String) -> ApiResult<Json<HashMap<String, String>>> {
+    if code == "en" {
+        return Ok(Json(HashMap::new()));
+    }
+    let locale = if valid_code(&code) { load_locale(&code) } else { None };
+    match locale {
+        Some(l) => Ok(Json(l.strings)),
+        None => Err(ApiError::not_found("no_such_language", format!("No translations for {:?}", code))),
+    }
+}
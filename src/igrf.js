@@ -0,0 +1,101 @@
+"use strict";
+
+/* The IGRF page. Inputs are named after their path in the IGRF, like
+   "venue.city". */
+
+function fields() {
+    return document.getElementById('igrf').querySelectorAll('[name]');
+}
+
+function getPath(obj, path) {
+    return path.split('.').reduce(function (o, k) { return o ? o[k] : undefined; }, obj);
+}
+
+function setPath(obj, path, value) {
+    var keys = path.split('.');
+    var last = keys.pop();
+    keys.forEach(function (k) {
+        if (!obj[k]) obj[k] = {};
+        obj = obj[k];
+    });
+    obj[last] = value;
+}
+
+function show(state) {
+    var inputs = fields();
+    for (var i = 0; i < inputs.length; i++) {
+        var value = getPath(state.igrf, inputs[i].name);
+        inputs[i].value = value === undefined ? '' : value;
+        inputs[i].disabled = state.final_at !== null;
+    }
+    var status = document.getElementById('status');
+    if (state.final_at !== null) {
+        status.textContent = I18n.t('Final since {0}', new Date(state.final_at).toLocaleString());
+        status.classList.add('final');
+    } else {
+        status.textContent = '';
+        status.classList.remove('final');
+    }
+    document.getElementById('save').disabled = state.final_at !== null;
+    document.getElementById('final').disabled = state.final_at !== null;
+    var crew = document.getElementById('crew');
+    crew.innerHTML = '';
+    state.officials.forEach(function (o) {
+        var li = document.createElement('li');
+        li.textContent = o.name + ' (' + o.positions.map(function (p) {
+            return p.replace(/_/g, ' ');
+        }).join(', ') + ')';
+        crew.appendChild(li);
+    });
+    if (!state.officials.length) {
+        var li = document.createElement('li');
+        li.textContent = I18n.t('None yet');
+        crew.appendChild(li);
+    }
+    var missing = document.getElementById('missing');
+    missing.innerHTML = '';
+    if (state.final_at === null) {
+        state.missing.forEach(function (m) {
+            var li = document.createElement('li');
+            li.textContent = I18n.t('{0} is required', I18n.t(m));
+            missing.appendChild(li);
+        });
+    }
+}
+
+function request(method, url, body) {
+    var xhr = new XMLHttpRequest();
+    xhr.open(method, url);
+    xhr.setRequestHeader('Content-Type', 'application/json');
+    OfficialSession.sign(xhr);
+    xhr.onreadystatechange = function () {
+        if (xhr.readyState !== XMLHttpRequest.DONE) return;
+        if (xhr.status === 200) {
+            show(JSON.parse(xhr.responseText));
+        } else {
+            var err = JSON.parse(xhr.responseText);
+            document.getElementById('status').textContent = err.message;
+        }
+    };
+    xhr.send(body === undefined ? null : JSON.stringify(body));
+}
+
+function collect() {
+    var igrf = {};
+    var inputs = fields();
+    for (var i = 0; i < inputs.length; i++) {
+        setPath(igrf, inputs[i].name, inputs[i].value);
+    }
+    return igrf;
+}
+
+document.getElementById('igrf').addEventListener('submit', function (e) {
+    e.preventDefault();
+    request('POST', '/igrf/data', collect());
+});
+
+document.getElementById('final').addEventListener('click', function () {
+    request('POST', '/igrf/final');
+});
+
+request('GET', '/igrf/data');
@@ -0,0 +1,67 @@
+/*! Routes for filling in the IGRF, before and after the game, and for
+    marking the game final once it's complete.
+ */
+
+use rocket::http::Status;
+use rocket_contrib::Json;
+
+use archive;
+use error::{ApiError, ApiResult};
+use gamestate::{FinalError, GameState};
+use gamestate::igrf::Igrf;
+use guard::{Game, MutGame};
+use officials::Official;
+
+#[derive(Serialize)]
+struct IgrfState {
+    igrf: Igrf,
+    /// When the game was marked final, in milliseconds since the epoch.
+    final_at: Option<u64>,
+    /// What still has to be filled in before the game can be final.
+    missing: Vec<String>,
+    /// The crew, as set on `/officials`, which the form lists.
+    officials: Vec<Official>,
+}
+
+fn igrf_state(game: &GameState) -> IgrfState {
+    IgrfState {
+        igrf: game.igrf().clone(), final_at: game.final_at(),
+        missing: game.final_missing().iter().map(|m| m.0.to_string()).collect(),
+        officials: game.officials().to_vec(),
+    }
+}
+
+#[get("/igrf/data")]
+fn get_igrf(game: Game) -> Json<IgrfState> {
+    Json(igrf_state(&game))
+}
+
+#[post("/igrf/data", format = "application/json", data = "<igrf>")]
+fn set_igrf(mut game: MutGame, igrf: Json<Igrf>) -> ApiResult<Json<IgrfState>> {
+    if !game.set_igrf(igrf.0) {
+        return Err(ApiError::new(Status::Conflict, "game_final",
+                                 "The game is final, so its IGRF can't be changed"));
+    }
+    Ok(Json(igrf_state(&game)))
+}
+
+/// Mark the game final, and archive it as it now stands.
+#[post("/igrf/final")]
+fn mark_final(mut game: MutGame) -> ApiResult<Json<IgrfState>> {
+    match game.mark_final() {
+        Ok(()) => (),
+        Err(FinalError::NotOver) =>
+            return Err(ApiError::new(Status::Conflict, "game_not_over",
+                                     "The game isn't over yet, so it can't be final")),
+        Err(FinalError::Incomplete(missing)) => {
+            let list = missing.iter().map(|m| m.to_string()).collect::<Vec<_>>();
+            return Err(ApiError::bad_request("incomplete_igrf", list.join("; ")));
+        },
+    }
+    let archived = archive::snapshot(&game);
+    let state = igrf_state(&game);
+    // Let go of the game before writing to the disk.
+    drop(game);
+    archive::save_or_log(archived);
+    Ok(Json(state))
+}
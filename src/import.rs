@@ -0,0 +1,294 @@
+/*! Importing a finished game, so that paperwork for a game scored on
+    another system can be fixed up on our score sheet and penalties pages
+    and exported again from `/derbyjson/game`. Games come in as the same
+    DerbyJSON game document we export, either on its own or wrapped as
+    `/derbyjson/game` sends it. The imported game replaces the current one
+    and stays in editing mode, with its clocks stopped.
+ */
+
+use std::collections::BTreeMap;
+
+use derbyjson;
+use rocket_contrib::Json;
+use serde_json::{self, Value};
+
+use config;
+use error::{ApiError, ApiResult};
+use events::TimeoutKind;
+use gamestate::{GameState, TimeoutEntry};
+use gamestate::igrf::Igrf;
+use gamestate::jamstate::{JamState, Team};
+use guard;
+use officials::Official;
+use roster;
+
+#[derive(Deserialize)]
+struct LineupIn {
+    skater: String,
+    position: String,
+}
+
+#[derive(Deserialize)]
+struct PassIn {
+    number: u8,
+    score: u8,
+    #[serde(default)]
+    pivot: bool,
+}
+
+#[derive(Deserialize)]
+struct PenaltyIn {
+    skater: String,
+    penalty: char,
+}
+
+#[derive(Deserialize)]
+struct TeamJamIn {
+    #[serde(default)]
+    lineup: Vec<LineupIn>,
+    #[serde(default)]
+    passes: Vec<PassIn>,
+    #[serde(default)]
+    penalties: Vec<PenaltyIn>,
+    #[serde(default)]
+    lead: bool,
+    #[serde(default)]
+    lost: bool,
+    #[serde(default)]
+    call: bool,
+    #[serde(default, rename = "starPass")]
+    star_pass: bool,
+    #[serde(default, rename = "scoreAdjustment")]
+    score_adjustment: i32,
+}
+
+#[derive(Deserialize)]
+struct JamIn {
+    teams: BTreeMap<String, TeamJamIn>,
+}
+
+#[derive(Deserialize)]
+struct PeriodIn {
+    number: u8,
+    jams: Vec<JamIn>,
+}
+
+#[derive(Deserialize)]
+struct TimeoutIn {
+    period: u8,
+    #[serde(rename = "afterJam")]
+    after_jam: u8,
+    kind: TimeoutKind,
+    team: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GameIn {
+    teams: BTreeMap<String, derbyjson::Team>,
+    #[serde(default)]
+    officials: Vec<derbyjson::Person>,
+    periods: Vec<PeriodIn>,
+    #[serde(default)]
+    timeouts: Vec<TimeoutIn>,
+    #[serde(default)]
+    metadata: Igrf,
+    created: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ImportBody {
+    Live { game: GameIn },
+    Game(GameIn),
+}
+
+fn bad<T, S: Into<String>>(message: S) -> ApiResult<T> {
+    Err(ApiError::bad_request("bad_game", message))
+}
+
+fn parse_team(key: &str) -> ApiResult<Team> {
+    match key {
+        "home" => Ok(Team::Home),
+        "away" => Ok(Team::Away),
+        _ => bad(format!("Unknown team {:?}", key)),
+    }
+}
+
+fn skater(roster: &roster::Team, number: &str) -> ApiResult<usize> {
+    match roster.skater_index(number) {
+        Some(idx) => Ok(idx),
+        None => bad(format!("No skater {:?} on the {} roster", number, roster.name)),
+    }
+}
+
+fn import_team_jam(jam: &mut JamState, team: Team, roster: &roster::Team, tj: TeamJamIn)
+                   -> ApiResult<()> {
+    let state = &mut jam[team];
+    // Without a pivot, the fourth blocker goes in the pivot's slot.
+    let no_pivot = !tj.lineup.iter().any(|l| l.position == "pivot");
+    let mut blocker_slots = if no_pivot { vec![2, 3, 4, 1] } else { vec![2, 3, 4] }.into_iter();
+    for l in &tj.lineup {
+        let slot = match l.position.as_str() {
+            "jammer" => 0,
+            "pivot" => 1,
+            "blocker" => match blocker_slots.next() {
+                Some(slot) => slot,
+                None => return bad("Too many blockers in a lineup"),
+            },
+            p => return bad(format!("Unknown position {:?}", p)),
+        };
+        if slot == 1 && no_pivot {
+            state.set_no_pivot(true);
+        }
+        state.set_lineup_slot(slot, Some(skater(roster, &l.skater)?));
+    }
+    // The jammer's trips go in before the star pass and the pivot's
+    // after it, so that set_score puts each on the right side of it.
+    for pass in tj.passes.iter().filter(|p| !p.pivot) {
+        state.set_score(pass.number, pass.score);
+    }
+    state.set_starpass(tj.star_pass);
+    for pass in tj.passes.iter().filter(|p| p.pivot) {
+        state.set_score(pass.number, pass.score);
+    }
+    for p in &tj.penalties {
+        if !state.add_penalty(skater(roster, &p.skater)?, p.penalty) {
+            return bad(format!("{:?} is not a penalty code", p.penalty));
+        }
+    }
+    state.set_lost(tj.lost);
+    state.set_lead(tj.lead);
+    state.set_call(tj.call);
+    state.set_score_adjust(tj.score_adjustment);
+    Ok(())
+}
+
+fn import(doc: GameIn) -> ApiResult<GameState> {
+    let mut teams = doc.teams;
+    let home = match teams.remove("home") {
+        Some(t) => roster::Team::from_derbyjson(t),
+        None => return bad("The game has no home team"),
+    };
+    let away = match teams.remove("away") {
+        Some(t) => roster::Team::from_derbyjson(t),
+        None => return bad("The game has no away team"),
+    };
+    let mut periods = doc.periods;
+    periods.sort_by_key(|p| p.number);
+    if periods.iter().any(|p| p.number < 1 || p.number > 2) {
+        return bad("Games have periods 1 and 2");
+    }
+    let mut jams = Vec::new();
+    let mut second_period_start = 0;
+    for period in periods {
+        if period.number == 2 {
+            second_period_start = jams.len();
+        }
+        for jam_in in period.jams {
+            let mut jam = JamState::default();
+            jam.untimed = true;
+            for (key, tj) in jam_in.teams {
+                let team = parse_team(&key)?;
+                let roster = if team == Team::Home { &home } else { &away };
+                import_team_jam(&mut jam, team, roster, tj)?;
+            }
+            jams.push(jam);
+        }
+    }
+    // Games are exported with the jam being lined up for on the end. If
+    // nothing has happened in it, it wasn't played.
+    if let Some(last) = jams.last_mut() {
+        if last.team1.lined_up_only() && last.team2.lined_up_only() {
+            last.untimed = false;
+        }
+    }
+    let mut timeouts = Vec::new();
+    for t in doc.timeouts {
+        let team = match t.team {
+            Some(ref key) => Some(parse_team(key)?),
+            None => None,
+        };
+        timeouts.push(TimeoutEntry {
+            period: t.period, after_jam: t.after_jam, kind: t.kind, team: team,
+        });
+    }
+    let officials = doc.officials.into_iter().filter_map(Official::from_derbyjson).collect();
+    let rules = config::get().ruleset;
+    let mut game = GameState::imported(home, away, rules, jams, second_period_start, timeouts);
+    if let Some(created) = doc.created {
+        game.set_created(created);
+    }
+    game.set_igrf(doc.metadata);
+    game.set_officials(officials);
+    Ok(game)
+}
+
+/// Load a game document that's already been parsed as JSON, such as one
+/// from the archive.
+pub fn from_value(value: Value) -> ApiResult<GameState> {
+    match serde_json::from_value(value) {
+        Ok(ImportBody::Live { game }) | Ok(ImportBody::Game(game)) => import(game),
+        Err(e) => bad(format!("Bad game document: {}", e)),
+    }
+}
+
+/// Replace the current game with an imported one, for editing.
+#[post("/import", format = "application/json", data = "<doc>")]
+fn import_game(doc: Json<ImportBody>) -> ApiResult<&'static str> {
+    let doc = match doc.0 {
+        ImportBody::Live { game } => game,
+        ImportBody::Game(game) => game,
+    };
+    guard::set_game(import(doc)?);
+    Ok("success")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use serde_json;
+    use derbylive;
+    use gamestate::GameState;
+    use gamestate::jamstate::Team;
+    use gamestate::ruleset::Ruleset;
+    use roster;
+
+    #[test]
+    fn test_round_trip() {
+        let mut game = GameState::new(roster::Team::new(String::from("Home")),
+                                      roster::Team::new(String::from("Away")),
+                                      Duration::new(0, 0), Ruleset::default());
+        game.set_created(1234);
+        game.start_jam();
+        {
+            let tj = &mut game.cur_jam_mut()[Team::Home];
+            tj.set_score(2, 4);
+            tj.set_starpass(true);
+            tj.set_score(3, 2);
+        }
+        game.stop_jam();
+        game.set_score(Team::Away, 3);
+
+        let doc = serde_json::to_value(&derbylive::game_doc(&game)).unwrap();
+        let imported = super::from_value(doc).ok().unwrap();
+        assert_eq!(imported.created(), 1234);
+        // The jam that was played, and the one lined up after it, which
+        // still hasn't been.
+        assert_eq!(imported.jams().len(), 2);
+        assert_eq!(imported.jamidx_to_periodjam(0), (1, 1));
+        assert_eq!(imported.jamidx_to_periodjam(1), (1, 2));
+        assert!(imported.jams()[0].played());
+        assert!(!imported.jams()[1].played());
+        assert_eq!(imported.total_score(), (6, 3));
+        let tj = &imported.jams()[0][Team::Home];
+        assert_eq!(tj.jammer_trips(), &[4]);
+        assert_eq!(tj.pivot_trips(), &[2]);
+
+        // And going round again doesn't add any.
+        let doc = serde_json::to_value(&derbylive::game_doc(&imported)).unwrap();
+        let again = super::from_value(doc).ok().unwrap();
+        assert_eq!(again.jams().len(), 2);
+        assert!(!again.jams()[1].played());
+        assert_eq!(again.total_score(), (6, 3));
+    }
+}
@@ -0,0 +1,132 @@
+"use strict";
+
+/* The lineup tracker. Rows are kept for every jam of the game and filled
+   in from /lineups/update, except for whatever is being typed in. */
+
+var BOX_MARKS = ['/', 'X', 'S', '$', '+', '-', '3'];
+var TEAM_NUMBERS = { home: 1, away: 2 };
+var lastUpdate = [];
+
+function team() {
+    return document.querySelector('input[name=team]:checked').value;
+}
+
+function jamCommand(jam, cmd) {
+    var xhr = new XMLHttpRequest();
+    xhr.open('POST', '/jam/' + jam + '/' + TEAM_NUMBERS[team()] + '/command');
+    xhr.setRequestHeader('Content-Type', 'application/json');
+    OfficialSession.sign(xhr);
+    xhr.onreadystatechange = function () {
+        if (xhr.readyState !== XMLHttpRequest.DONE) return;
+        if (xhr.status !== 200) {
+            alert(JSON.parse(xhr.responseText).message);
+        }
+        update();
+    };
+    xhr.send(JSON.stringify(cmd));
+}
+
+/* Box trips are typed as their marks, in order, like "/ X" or "S$". */
+function parseMarks(text) {
+    return text.toUpperCase().split('').filter(function (c) {
+        return BOX_MARKS.indexOf(c) !== -1;
+    });
+}
+
+function createRow(body) {
+    var template = document.getElementById('lineuprow').content;
+    var row = document.importNode(template, true).children[0];
+    row.querySelector('.nopivot').addEventListener('change', function () {
+        jamCommand(row.dataset.jam, { "no-pivot": this.checked });
+    });
+    var skaters = row.querySelectorAll('input.skater');
+    var boxes = row.querySelectorAll('input.box');
+    for (var i = 0; i < skaters.length; i++) {
+        (function (slot) {
+            skaters[slot].addEventListener('change', function () {
+                var number = this.value.trim();
+                jamCommand(row.dataset.jam,
+                           { lineup: { slot: slot, skater: number === '' ? null : number } });
+            });
+            boxes[slot].addEventListener('change', function () {
+                jamCommand(row.dataset.jam,
+                           { "box-trips": { slot: slot, marks: parseMarks(this.value) } });
+            });
+        })(i);
+    }
+    return body.appendChild(row);
+}
+
+function fill(row, jam, lineup) {
+    row.dataset.jam = jam.jam;
+    row.querySelector('.jamnum').textContent = jam.number;
+    row.classList.toggle('nopivot', lineup.no_pivot);
+    row.querySelector('.nopivot').checked = lineup.no_pivot;
+    var skaters = row.querySelectorAll('input.skater');
+    var boxes = row.querySelectorAll('input.box');
+    lineup.slots.forEach(function (slot, i) {
+        if (skaters[i] !== document.activeElement) skaters[i].value = slot.skater;
+        if (boxes[i] !== document.activeElement) boxes[i].value = slot.boxtrips.join(' ');
+    });
+}
+
+function refresh(jams) {
+    lastUpdate = jams;
+    var body = document.querySelector('#lineups tbody');
+    var rows = body.querySelectorAll('tr.jam');
+    var headers = body.querySelectorAll('tr.periodstart');
+    /* Start again if the periods have moved, as after a correction. */
+    var periods = jams.filter(function (jam) { return jam.number === 1; }).length;
+    if (headers.length !== periods || rows.length > jams.length) {
+        body.innerHTML = '';
+        rows = [];
+    }
+    jams.forEach(function (jam, i) {
+        var row = rows[i];
+        if (!row) {
+            if (jam.number === 1) {
+                var header = body.insertRow();
+                header.className = 'periodstart';
+                var cell = header.insertCell();
+                cell.className = 'period';
+                cell.colSpan = 12;
+                cell.textContent = I18n.t('Period {0}', jam.period);
+            }
+            row = createRow(body);
+            row.classList.add('jam');
+        }
+        fill(row, jam, jam[team()]);
+    });
+}
+
+function update() {
+    var xhr = new XMLHttpRequest();
+    xhr.open('GET', '/lineups/update');
+    xhr.onreadystatechange = function () {
+        if (xhr.readyState === XMLHttpRequest.DONE && xhr.status === 200) {
+            refresh(JSON.parse(xhr.responseText));
+        }
+    };
+    xhr.send();
+}
+
+function loadName(teamkey) {
+    var xhr = new XMLHttpRequest();
+    xhr.open('GET', '/gameroster/' + TEAM_NUMBERS[teamkey]);
+    xhr.onreadystatechange = function () {
+        if (xhr.readyState === XMLHttpRequest.DONE && xhr.status === 200) {
+            document.getElementById(teamkey + 'name').textContent =
+                JSON.parse(xhr.responseText).name;
+        }
+    };
+    xhr.send();
+}
+
+var teamButtons = document.getElementsByName('team');
+for (var i = 0; i < teamButtons.length; i++) {
+    teamButtons[i].addEventListener('change', function () { refresh(lastUpdate); });
+}
+loadName('home');
+loadName('away');
+update();
+window.setInterval(update, 1000);
@@ -0,0 +1,61 @@
+/*! The lineup tracker. Each jam's lineups are kept on the jam's
+    `TeamJamState` as on the WFTDA lineup sheet: the jammer, the pivot
+    (or a fourth blocker, if there's no pivot) and three blockers, each
+    with their box trips. Changes go through `/jam/<jam>/<team>/command`
+    like the rest of the jam; this is just the view of them for the
+    `/lineups` page.
+ */
+
+use rocket_contrib::Json;
+
+use gamestate::GameState;
+use gamestate::jamstate::{BoxMark, Team, TeamJamState, LINEUP_SLOTS};
+use guard::Game;
+
+#[derive(Serialize)]
+struct SlotView {
+    /// The skater's number, or empty.
+    skater: String,
+    boxtrips: Vec<BoxMark>,
+}
+
+#[derive(Serialize)]
+struct TeamLineup {
+    no_pivot: bool,
+    /// Jammer, pivot, then blockers.
+    slots: Vec<SlotView>,
+}
+
+#[derive(Serialize)]
+struct JamLineups {
+    /// The jam of the game, numbered from 1, for jam commands.
+    jam: usize,
+    period: u8,
+    number: u8,
+    home: TeamLineup,
+    away: TeamLineup,
+}
+
+fn team_lineup(game: &GameState, team: Team, tj: &TeamJamState) -> TeamLineup {
+    let skaters = &game.roster(team).skaters;
+    TeamLineup {
+        no_pivot: tj.no_pivot(),
+        slots: (0..LINEUP_SLOTS).map(|slot| SlotView {
+            skater: tj.lineup_slot(slot).and_then(|idx| skaters.get(idx))
+                .map_or(String::new(), |s| s.number.clone()),
+            boxtrips: tj.box_trips(slot).to_vec(),
+        }).collect(),
+    }
+}
+
+#[get("/lineups/update")]
+fn get_lineups(game: Game) -> Json<Vec<JamLineups>> {
+    Json(game.jams().iter().enumerate().map(|(idx, jam)| {
+        let (period, number) = game.jamidx_to_periodjam(idx);
+        JamLineups {
+            jam: idx + 1, period: period, number: number,
+            home: team_lineup(&game, Team::Home, &jam[Team::Home]),
+            away: team_lineup(&game, Team::Away, &jam[Team::Away]),
+        }
+    }).collect())
+}
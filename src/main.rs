@@ -10,44 +10,100 @@ extern crate serde;
 extern crate derbyjson;
 extern crate handlebars;
 extern crate chrono;
+extern crate hyper;
+extern crate hyper_native_tls;
+extern crate ws;
+extern crate rand;
 
 use rocket_contrib::Json;
 
 use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process;
 use std::time::Duration;
 use rocket::request::Form;
-use rocket::response::Redirect;
-use rocket::http::RawStr;
+use rocket::response::{NamedFile, Redirect};
+use rocket::http::{RawStr, Status};
+use rocket::config::{Config as RocketConfig, Environment};
 
+mod api;
+mod igrf;
+mod lineups;
+mod reports;
+mod archive;
+mod import;
+mod crg;
+mod derbylive;
+mod error;
 mod gamestate;
 mod roster;
 mod staticpages;
 mod guard;
 mod timetoderby;
+mod config;
+mod overlay;
+mod media;
+mod events;
+mod webhooks;
+mod metrics;
+mod officials;
+mod i18n;
 
-use gamestate::{Penalty, ActiveClock};
-use gamestate::jamstate::{Team,TeamJamState};
+use error::{ApiError, ApiResult};
+use gamestate::{GameState, Penalty, PenaltyError, ActiveClock, ClockState, CorrectionEntry};
+use gamestate::ruleset::Ruleset;
+use gamestate::jamstate::{Team, TeamJamState, BoxMark, LINEUP_SLOTS};
+use gamestate::stats::GameStats;
 use guard::{Game, MutGame};
+use events::TimeoutKind;
 use timetoderby::*;
 
 #[derive(Deserialize)]
 struct PenaltyCmd {
     skater: String,
     code: char,
+    /// The jam of the game to put the penalty in, numbered from 1, if not
+    /// the current one.
+    jam: Option<usize>,
+}
+
+/// Refuse changes to a game that's been marked final.
+fn check_not_final(game: &GameState) -> ApiResult<()> {
+    if game.final_at().is_some() {
+        return Err(ApiError::new(Status::Conflict, "game_final",
+                                 "The game is final, so it can't be changed"));
+    }
+    Ok(())
+}
+
+fn record_penalty(game: &mut GameState, team: Team, jam: Option<usize>, skater: &str,
+                  code: char) -> ApiResult<()> {
+    check_not_final(game)?;
+    match game.penalty(team, jam, skater, code) {
+        Ok(()) => Ok(()),
+        Err(PenaltyError::UnknownSkater) => Err(ApiError::bad_request(
+            "unknown_skater", format!("No skater {:?} on the roster", skater))),
+        Err(PenaltyError::UnknownCode) => Err(ApiError::bad_request(
+            "unknown_penalty", format!("{:?} is not a penalty code", code))),
+        Err(PenaltyError::NoSuchJam) => Err(ApiError::not_found(
+            "no_such_jam", format!("There is no jam {}", jam.unwrap_or(0)))),
+    }
 }
 
 #[post("/penalties/<team>", format = "application/json", data = "<cmd>")]
-fn add_penalty(mut game: MutGame, team: Team, cmd: Json<PenaltyCmd>)
-               -> Json<HashMap<String, Vec<Penalty>>>
+fn add_penalty(mut game: MutGame, team: Result<Team, &'static str>, cmd: Json<PenaltyCmd>)
+               -> ApiResult<Json<HashMap<String, Vec<Penalty>>>>
 {
-    game.penalty(team, cmd.skater.as_str(), cmd.code);
-    Json(game.team_penalties(team))
+    let team = error::team(team)?;
+    record_penalty(&mut game, team, cmd.jam, &cmd.skater, cmd.code)?;
+    Ok(Json(game.team_penalties(team)))
 }
 
 #[get("/penalties/<team>")]
-fn get_penalties(game: Game, team: Team) -> Json<HashMap<String, Vec<Penalty>>>
+fn get_penalties(game: Game, team: Result<Team, &'static str>)
+                 -> ApiResult<Json<HashMap<String, Vec<Penalty>>>>
 {
-    Json(game.team_penalties(team))
+    Ok(Json(game.team_penalties(error::team(team)?)))
 }
 
 #[derive(Serialize)]
@@ -58,12 +114,16 @@ struct ScoreUpdate {
     activeclock: ActiveClock,
     timeouts: (u8, u8),
     reviews: (u8, u8),
+    /// When this update was made, in milliseconds since the epoch, so
+    /// that displays can interpolate the clocks from there.
+    server_time: u64,
+    clocks: ClockState,
 }
 
 #[get("/score/update")]
 fn scoreupdate(game: Game) -> Json<ScoreUpdate> {
     let cur_jam = game.cur_jam();
-    let jamscore = if cur_jam.starttime.is_some() {
+    let jamscore = if cur_jam.played() {
         cur_jam.jam_score()
     } else {
         match game.prev_jam() {
@@ -76,6 +136,7 @@ fn scoreupdate(game: Game) -> Json<ScoreUpdate> {
         score: game.total_score(), jamscore: jamscore,
         gameclock: game.get_time(), activeclock: game.get_active_clock(),
         reviews: game.reviews(), timeouts: game.timeouts(),
+        server_time: events::now_millis(), clocks: game.clock_state(),
     })
 }
 
@@ -83,38 +144,167 @@ fn scoreupdate(game: Game) -> Json<ScoreUpdate> {
 #[derive(Deserialize)]
 enum UpdateCommand {
     score_adj(i8, i8),
-    //score_set(i8, i8),
+    score_set(u32, u32),
     set_time(u16),
+    /// Corrections to the rest of the game state. Clock values are in
+    /// seconds.
+    set_active_time(u16),
+    set_period(u8),
+    set_jam(u8),
+    set_timeouts(Team, u8),
+    set_reviews(Team, u8),
     start_jam,
     stop_jam,
+    /// Start or stop the jam as of a time in the past, in milliseconds of
+    /// server time, for when the command took a while to get here.
+    start_jam_at(u64),
+    stop_jam_at(u64),
     team_timeout(Team),
     star_pass(Team),
     official_timeout,
     official_review(Team),
+    end_timeout,
+    convert_timeout(TimeoutKind, Option<Team>),
     review_lost(Team),
     review_retained(Team),
 }
 
-#[post("/score/update", format = "application/json", data = "<cmd>")]
-fn post_score(mut game: MutGame, cmd: Json<UpdateCommand>) -> &'static str
-{
-    match cmd.0 {
+/// How far a timestamped command's time may be from the server's clock.
+/// Anything further off is probably a confused client clock rather than
+/// lag, so it's refused instead of guessed at.
+const MAX_COMMAND_LAG: u64 = 5000;
+
+/// How long ago a command timestamped `at` was given. A time a little
+/// ahead of the server's clock counts as now.
+fn command_lag(at: u64) -> ApiResult<Duration> {
+    let now = events::now_millis();
+    if at.saturating_add(MAX_COMMAND_LAG) < now || at > now.saturating_add(MAX_COMMAND_LAG) {
+        return Err(ApiError::bad_request(
+            "bad_timestamp", format!("Timestamp {} is more than {} ms from the server's clock ({})",
+                                     at, MAX_COMMAND_LAG, now)));
+    }
+    Ok(Duration::from_millis(now.saturating_sub(at)))
+}
+
+#[derive(Serialize)]
+struct ServerTime {
+    server_time: u64,
+}
+
+/// The server's clock, for clients that want to timestamp their commands.
+#[get("/time")]
+fn server_time() -> Json<ServerTime> {
+    Json(ServerTime { server_time: events::now_millis() })
+}
+
+/// A timeout that couldn't be called because there's no game going.
+fn timeout_called(called: bool) -> ApiResult<()> {
+    if called {
+        Ok(())
+    } else {
+        Err(ApiError::new(Status::Conflict, "no_timeout",
+                          "There's no game going to call a timeout in"))
+    }
+}
+
+/// The longest an active clock can be set to.
+const MAX_ACTIVE_SECS: u16 = 60 * 60;
+
+/// Check a command's values before it gets near the game state.
+fn check_command(cmd: &UpdateCommand, rules: &Ruleset) -> ApiResult<()> {
+    let bad = |code, message: String| Err(ApiError::bad_request(code, message));
+    match *cmd {
+        UpdateCommand::set_time(secs) if secs as u64 > rules.period_length.as_secs() =>
+            bad("bad_time", format!("The period clock can't be set to more than {} seconds",
+                                    rules.period_length.as_secs())),
+        UpdateCommand::set_active_time(secs) if secs > MAX_ACTIVE_SECS =>
+            bad("bad_time", format!("The active clock can't be set to more than {} seconds",
+                                    MAX_ACTIVE_SECS)),
+        UpdateCommand::set_period(period) if period < 1 || period > 2 =>
+            bad("bad_period", format!("There is no period {}", period)),
+        UpdateCommand::set_jam(0) =>
+            bad("bad_jam", String::from("Jams are numbered from 1")),
+        UpdateCommand::set_timeouts(_, n) if n > rules.timeouts =>
+            bad("bad_allowance", format!("Teams only get {} timeouts", rules.timeouts)),
+        UpdateCommand::set_reviews(_, n) if n > rules.reviews =>
+            bad("bad_allowance", format!("Teams only get {} reviews", rules.reviews)),
+        UpdateCommand::convert_timeout(TimeoutKind::Team, None) |
+        UpdateCommand::convert_timeout(TimeoutKind::Review, None) =>
+            bad("bad_team", String::from("Team timeouts and reviews need a team")),
+        _ => Ok(()),
+    }
+}
+
+impl UpdateCommand {
+    /// Whether the command runs the clocks or moves the game on, which
+    /// can't be done to a game being edited.
+    fn moves_clock(&self) -> bool {
+        match *self {
+            UpdateCommand::start_jam | UpdateCommand::stop_jam |
+            UpdateCommand::start_jam_at(_) | UpdateCommand::stop_jam_at(_) |
+            UpdateCommand::team_timeout(_) | UpdateCommand::official_timeout |
+            UpdateCommand::official_review(_) | UpdateCommand::end_timeout |
+            UpdateCommand::convert_timeout(..) | UpdateCommand::set_time(_) |
+            UpdateCommand::set_active_time(_) | UpdateCommand::set_period(_) |
+            UpdateCommand::set_jam(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Check a command and carry it out.
+fn apply_command(game: &mut GameState, cmd: UpdateCommand) -> ApiResult<()> {
+    check_command(&cmd, game.rules())?;
+    check_not_final(game)?;
+    if game.editing() && cmd.moves_clock() {
+        return Err(ApiError::new(Status::Conflict, "editing",
+                                 "The game is being edited, so its clocks can't run"));
+    }
+    match cmd {
         UpdateCommand::score_adj(a1, a2) =>
             game.cur_jam_mut().adj_score(a1, a2),
         UpdateCommand::start_jam => game.start_jam(),
         UpdateCommand::stop_jam => game.stop_jam(),
-        UpdateCommand::official_timeout => game.official_timeout(),
-        UpdateCommand::team_timeout(team) => { game.team_timeout(team); },
-        UpdateCommand::official_review(team) => { game.official_review(team); }
-        UpdateCommand::star_pass(team) =>
-            game.cur_jam_mut()[team].set_starpass(true),
+        UpdateCommand::start_jam_at(at) => game.start_jam_at(command_lag(at)?),
+        UpdateCommand::stop_jam_at(at) => game.stop_jam_at(command_lag(at)?),
+        UpdateCommand::official_timeout => timeout_called(game.official_timeout())?,
+        UpdateCommand::team_timeout(team) => timeout_called(game.team_timeout(team))?,
+        UpdateCommand::official_review(team) => timeout_called(game.official_review(team))?,
+        UpdateCommand::end_timeout => game.end_timeout(),
+        UpdateCommand::convert_timeout(kind, team) => { game.convert_timeout(kind, team); },
+        UpdateCommand::star_pass(team) => {
+            game.cur_jam_mut()[team].set_starpass(true);
+            let jam = game.jams().len();
+            game.jam_changed(jam, team);
+        },
+        UpdateCommand::score_set(s1, s2) => {
+            if game.total_score().0 != s1 { game.set_score(Team::Home, s1) }
+            if game.total_score().1 != s2 { game.set_score(Team::Away, s2) }
+        },
         UpdateCommand::set_time(secs) =>
             game.set_time(Duration::new(secs as u64, 0)),
+        UpdateCommand::set_active_time(secs) =>
+            game.set_active_time(Duration::new(secs as u64, 0)),
+        UpdateCommand::set_period(period) => game.set_period(period),
+        UpdateCommand::set_jam(jam) => game.set_jamnum(jam),
+        UpdateCommand::set_timeouts(team, n) => game.set_timeouts(team, n),
+        UpdateCommand::set_reviews(team, n) => game.set_reviews(team, n),
         UpdateCommand::review_lost(team) => game.review_lost(team),
-        // don't need to actually do anything for this case.
-        UpdateCommand::review_retained(_) => (),
-    }; 
-    "success"
+        UpdateCommand::review_retained(team) => game.review_retained(team),
+    };
+    Ok(())
+}
+
+#[post("/score/update", format = "application/json", data = "<cmd>")]
+fn post_score(mut game: MutGame, cmd: Json<UpdateCommand>) -> ApiResult<&'static str>
+{
+    apply_command(&mut game, cmd.0)?;
+    Ok("success")
+}
+
+#[get("/corrections")]
+fn get_corrections(game: Game) -> Json<Vec<CorrectionEntry>> {
+    Json(game.corrections().to_vec())
 }
 
 #[derive(Deserialize)]
@@ -125,20 +315,94 @@ enum JamCommand {
     Call(bool),
     Starpass(bool),
     ScoringTrip { trip: u8, points: u8 },
+    /// Put a skater, by number, in a lineup slot, or empty it.
+    Lineup { slot: usize, skater: Option<String> },
+    BoxTrips { slot: usize, marks: Vec<BoxMark> },
+    NoPivot(bool),
+}
+
+/// Trips are numbered from 1, the initial trip, to the last column of the
+/// score sheet. The initial trip doesn't score, so scoring trips start at
+/// 2.
+const FIRST_SCORING_TRIP: u8 = 2;
+const MAX_TRIP: u8 = 10;
+/// No more points than there are opposing skaters.
+const MAX_TRIP_POINTS: u8 = 5;
+
+fn no_such_jam(jam: usize) -> ApiError {
+    ApiError::not_found("no_such_jam", format!("There is no jam {}", jam))
+}
+
+/// A team's side of a jam, by jam number.
+fn team_jam_mut(game: &mut GameState, jam: usize, team: Team) -> ApiResult<&mut TeamJamState> {
+    match game.get_jam_mut(jam) {
+        Some(j) => Ok(&mut j[team]),
+        None => Err(no_such_jam(jam)),
+    }
+}
+
+/// Check a jam command's values before it gets near the game state.
+fn check_jam_command(cmd: &JamCommand) -> ApiResult<()> {
+    match *cmd {
+        JamCommand::ScoringTrip { trip, .. } if trip < FIRST_SCORING_TRIP || trip > MAX_TRIP =>
+            Err(ApiError::bad_request("bad_trip", format!("There is no scoring trip {}", trip))),
+        JamCommand::ScoringTrip { points, .. } if points > MAX_TRIP_POINTS =>
+            Err(ApiError::bad_request(
+                "bad_points", format!("A trip can't score {} points", points))),
+        JamCommand::Lineup { slot, .. } | JamCommand::BoxTrips { slot, .. }
+            if slot >= LINEUP_SLOTS =>
+            Err(ApiError::bad_request(
+                "bad_slot", format!("There is no lineup slot {}", slot))),
+        _ => Ok(()),
+    }
 }
 
 #[post("/jam/<jam>/<team>/command", format = "application/json", data = "<cmd>")]
-fn jam_command(mut game: MutGame, jam: usize, team: Team, cmd: Json<JamCommand>) -> &'static str
+fn jam_command(mut game: MutGame, jam: usize, team: Result<Team, &'static str>,
+               cmd: Json<JamCommand>) -> ApiResult<&'static str>
 {
-    let ref mut teamjam = game.get_jam_mut(jam)[team];
+    let team = error::team(team)?;
+    check_not_final(&game)?;
+    check_jam_command(&cmd.0)?;
+    let skater = match cmd.0 {
+        JamCommand::Lineup { skater: Some(ref number), .. } => {
+            match game.roster(team).skater_index(number) {
+                Some(idx) => Some(idx),
+                None => return Err(ApiError::bad_request(
+                    "unknown_skater", format!("No skater {:?} on the roster", number))),
+            }
+        },
+        _ => None,
+    };
+    let trip = match cmd.0 {
+        JamCommand::ScoringTrip { .. } => true,
+        _ => false,
+    };
     match cmd.0 {
-        JamCommand::Lead(yesno) => teamjam.set_lead(yesno),
-        JamCommand::Call(yesno) => teamjam.set_call(yesno),
-        JamCommand::Lost(yesno) => teamjam.set_lost(yesno),
-        JamCommand::Starpass(yesno) => teamjam.set_starpass(yesno),
-        JamCommand::ScoringTrip { trip, points } => teamjam.set_score(trip, points),
+        JamCommand::Lead(yesno) => team_jam_mut(&mut game, jam, team)?.set_lead(yesno),
+        JamCommand::Call(yesno) => team_jam_mut(&mut game, jam, team)?.set_call(yesno),
+        JamCommand::Lost(yesno) => team_jam_mut(&mut game, jam, team)?.set_lost(yesno),
+        JamCommand::Starpass(yesno) => team_jam_mut(&mut game, jam, team)?.set_starpass(yesno),
+        JamCommand::ScoringTrip { trip, points } => {
+            if !game.scoring_trip(jam, team, trip, points) {
+                return Err(no_such_jam(jam));
+            }
+        },
+        JamCommand::Lineup { slot, .. } => {
+            team_jam_mut(&mut game, jam, team)?.set_lineup_slot(slot, skater);
+            game.carry_over(jam, team);
+        },
+        JamCommand::BoxTrips { slot, marks } => {
+            team_jam_mut(&mut game, jam, team)?.set_box_trips(slot, marks);
+            game.carry_over(jam, team);
+        },
+        JamCommand::NoPivot(yesno) => team_jam_mut(&mut game, jam, team)?.set_no_pivot(yesno),
     };
-    "success"
+    // Trips have events of their own.
+    if !trip {
+        game.jam_changed(jam, team);
+    }
+    Ok("success")
 }
 
 #[get("/scoresheet/update")]
@@ -163,39 +427,157 @@ struct StartGameCommand<'a> {
     ttd_secs: Option<u8>,
 }
 
+fn start_roster(name: &RawStr, defname: &str) -> ApiResult<roster::Team> {
+    roster::get_team(name, String::from(defname)).map_err(|e| {
+        ApiError::bad_request("bad_roster", format!("Can't load the {} roster: {}",
+                                                    defname.to_lowercase(), e))
+    })
+}
+
 #[post("/startgame", data = "<form>")]
-fn startgame<'a>(form: Form<'a, StartGameCommand<'a>>) -> Redirect
+fn startgame<'a>(form: Form<'a, StartGameCommand<'a>>) -> ApiResult<Redirect>
 {
     let cmd = form.get();
-    let team1 = roster::get_team(cmd.hometeam, String::from("Home")).unwrap(); // XXX
-    let team2 = roster::get_team(cmd.awayteam, String::from("Away")).unwrap(); // XXX
+    let team1 = start_roster(cmd.hometeam, "Home")?;
+    let team2 = start_roster(cmd.awayteam, "Away")?;
     let time = match cmd.timetype {
-        TimeType::TimeToDerby => Duration::new((cmd.ttd_hrs.unwrap_or_default() as u64) * 3600
-                                               + (cmd.ttd_mins.unwrap_or_default() as u64) * 60
-                                               + (cmd.ttd_secs.unwrap_or_default() as u64), 0),
+        TimeType::TimeToDerby => {
+            let (mins, secs) = (cmd.ttd_mins.unwrap_or_default(), cmd.ttd_secs.unwrap_or_default());
+            if mins >= 60 || secs >= 60 {
+                return Err(ApiError::bad_request("bad_time", "Bad time to derby"));
+            }
+            Duration::new((cmd.ttd_hrs.unwrap_or_default() as u64) * 3600
+                          + (mins as u64) * 60 + (secs as u64), 0)
+        },
         TimeType::StartAt => start_at_time(cmd.at_hrs.unwrap_or_default(),
                                            cmd.at_mins.unwrap_or_default(),
-                                           cmd.at_ampm).unwrap(),// XXX
+                                           cmd.at_ampm)
+            .map_err(|e| ApiError::bad_request("bad_time", e))?,
     };
     guard::start_game(team1, team2, time);
-    Redirect::to("/")
+    Ok(Redirect::to("/"))
+}
+
+#[get("/stats")]
+fn get_stats(game: Game) -> Json<GameStats> {
+    Json(game.stats())
 }
 
 #[get("/gameroster/<team>")]
-fn gameroster(game: Game, team: Team) -> Json<roster::Team> {
-    let skaters = game.roster(team);
-    Json(skaters.clone()) // ew. Why can't we serialize a ref?
+fn gameroster(game: Game, team: Result<Team, &'static str>) -> ApiResult<Json<roster::Team>> {
+    let skaters = game.roster(error::team(team)?);
+    Ok(Json(skaters.clone())) // ew. Why can't we serialize a ref?
+}
+
+#[derive(Deserialize)]
+struct TeamColorCmd {
+    color: Option<String>,
+}
+
+#[post("/gameroster/<team>/color", format = "application/json", data = "<cmd>")]
+fn set_team_color(mut game: MutGame, team: Result<Team, &'static str>, cmd: Json<TeamColorCmd>)
+                  -> ApiResult<Json<roster::Team>>
+{
+    let team = error::team(team)?;
+    let color = cmd.0.color;
+    if let Some(ref c) = color {
+        if !roster::valid_color(c) {
+            return Err(ApiError::bad_request("bad_color", format!("{:?} is not a colour", c)));
+        }
+    }
+    game.set_team_color(team, color);
+    Ok(Json(game.roster(team).clone()))
+}
+
+/// Team logos from the roster directory. Only images are served, so the
+/// rosters themselves stay private.
+#[get("/logos/<file..>")]
+fn logo(file: PathBuf) -> Option<NamedFile> {
+    if !media::is_image(&file) {
+        return None;
+    }
+    NamedFile::open(config::get().roster_dir.join(file)).ok()
 }
 
 fn main() {
-    rocket::ignite().mount(
+    let config = match config::from_env() {
+        Ok(Some(c)) => c,
+        Ok(None) => { print!("{}", config::USAGE); return }
+        Err(e) => {
+            eprintln!("derbyboard: {}\n\n{}", e, config::USAGE);
+            process::exit(2);
+        }
+    };
+    let rocket_config = RocketConfig::build(Environment::active().unwrap_or(Environment::Development))
+        .address(config.address.as_str())
+        .port(config.port)
+        .finalize();
+    let rocket_config = match rocket_config {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("derbyboard: bad server settings: {:?}", e);
+            process::exit(2);
+        }
+    };
+    config::set(config);
+    if let Err(e) = staticpages::check_templates() {
+        eprintln!("derbyboard: {}", e);
+        process::exit(2);
+    }
+
+    events::spawn_logger();
+    webhooks::spawn_dispatcher();
+    if let Err(e) = crg::spawn_server() {
+        eprintln!("derbyboard: {}", e);
+        process::exit(2);
+    }
+    archive::spawn_archiver();
+    rocket::custom(rocket_config, true).mount(
         "/",
-        routes![staticpages::index,  gameroster, startgame,
+        routes![staticpages::index,  gameroster, startgame, set_team_color, logo,
                 staticpages::penalties, staticpages::penaltiesjs, get_penalties,
-                staticpages::scoreboard, staticpages::scoreboardjs,
+                staticpages::scoreboard, staticpages::scoreboardjs, staticpages::clocksyncjs,
+                staticpages::i18njs, i18n::list_locales, i18n::get_locale,
                 staticpages::mobilejt, staticpages::mobilejtjs,
                 staticpages::scoresheet, staticpages::scoresheetjs,
                 get_scoresheet, jam_command,
-                scoreupdate, post_score, add_penalty]
-    ).launch();
+                get_stats, staticpages::boxscore,
+                staticpages::overlay, staticpages::overlayjs,
+                staticpages::overlaycontrol, overlay::overlay_update,
+                overlay::get_control, overlay::post_control,
+                staticpages::assets, staticpages::mediaadmin, media::get_media, media::get_file,
+                media::upload_file, media::delete_file, media::set_messages,
+                events::poll_events, webhooks::get_targets, webhooks::add_target,
+                webhooks::delete_target, webhooks::get_log, metrics::get_metrics,
+                scoreupdate, post_score, server_time, get_corrections, add_penalty,
+                api::get_game, api::get_jams, api::post_command, api::get_penalties,
+                api::post_penalty, api::openapi, derbylive::live_game,
+                derbylive::live_updates, import::import_game,
+                archive::archive_page, archive::archive_list, archive::archived_game,
+                archive::export_game, archive::archived_report, reports::game_report,
+                staticpages::igrf, staticpages::igrfjs, igrf::get_igrf, igrf::set_igrf,
+                igrf::mark_final, officials::get_officials, officials::set_officials,
+                officials::open_session, officials::list_sessions, officials::close_session,
+                officials::get_actions, staticpages::lineups, staticpages::lineupsjs,
+                lineups::get_lineups, staticpages::session, staticpages::sessionjs,
+                staticpages::sessionpagejs]
+    ).catch(errors![error::bad_request, error::not_found, error::no_game,
+                       error::internal_error])
+    .attach(metrics::RequestMetrics).launch();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_jam_command, JamCommand};
+
+    #[test]
+    fn test_check_trip() {
+        let trip = |trip| check_jam_command(&JamCommand::ScoringTrip { trip: trip, points: 4 });
+        assert!(trip(0).is_err());
+        // The initial trip doesn't score.
+        assert!(trip(1).is_err());
+        assert!(trip(2).is_ok());
+        assert!(trip(10).is_ok());
+        assert!(trip(11).is_err());
+    }
 }
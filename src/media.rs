@@ -0,0 +1,138 @@
+/*! Sponsor media for intermission and time to derby. Images are uploaded
+    into a local media directory, and text messages are stored alongside
+    them in `messages.json`. The scoreboard rotates through both while the
+    intermission or time to derby clock is running.
+ */
+
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use rocket::Data;
+use rocket::http::Status;
+use rocket::response::NamedFile;
+use rocket_contrib::Json;
+use serde_json;
+
+use config;
+use error::{ApiError, ApiResult};
+
+const MESSAGES_FILE: &'static str = "messages.json";
+/// Largest image we'll accept for upload.
+const UPLOAD_LIMIT: u64 = 8 * 1024 * 1024;
+/// Images we'll serve. Not SVG, which can carry script that would run as
+/// one of our own pages.
+const IMAGE_EXTENSIONS: [&'static str; 5] = ["png", "jpg", "jpeg", "gif", "webp"];
+
+#[derive(Serialize)]
+pub struct MediaList {
+    images: Vec<String>,
+    messages: Vec<String>,
+}
+
+/// Uploaded media lives in the data directory.
+fn media_dir() -> PathBuf {
+    config::get().data_dir.join("media")
+}
+
+fn invalid_input<T>(s: String) -> io::Result<T> {
+    Err(io::Error::new(io::ErrorKind::InvalidInput, s))
+}
+
+/// Check that a file name refers to an image directly inside the media
+/// directory, and return its path.
+fn image_path(name: &str) -> io::Result<PathBuf> {
+    if name.starts_with('.') || name.contains('/') || name.contains('\\') {
+        return invalid_input(format!("Bad media file name {:?}", name));
+    }
+    let path = media_dir().join(name);
+    if !is_image(&path) {
+        return invalid_input(format!("{:?} is not an image file", name));
+    }
+    Ok(path)
+}
+
+/// Whether a file has one of the image extensions.
+pub fn is_image(path: &Path) -> bool {
+    path.extension().and_then(|e| e.to_str()).map_or(false, |e| {
+        IMAGE_EXTENSIONS.contains(&e.to_lowercase().as_str())
+    })
+}
+
+fn load_messages() -> io::Result<Vec<String>> {
+    let path = media_dir().join(MESSAGES_FILE);
+    match File::open(path) {
+        Ok(f) => serde_json::from_reader(f).map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidData, e)
+        }),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(vec!()),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn list_media() -> io::Result<MediaList> {
+    let mut images = Vec::new();
+    let dir = media_dir();
+    if dir.is_dir() {
+        for entry in fs::read_dir(dir)? {
+            let name = entry?.file_name().to_string_lossy().into_owned();
+            if image_path(&name).is_ok() {
+                images.push(name);
+            }
+        }
+    }
+    images.sort();
+    Ok(MediaList { images: images, messages: load_messages()? })
+}
+
+#[get("/media/list")]
+fn get_media() -> ApiResult<Json<MediaList>> {
+    Ok(Json(list_media()?))
+}
+
+#[get("/media/files/<name>")]
+fn get_file(name: String) -> Option<NamedFile> {
+    image_path(&name).and_then(|p| NamedFile::open(p)).ok()
+}
+
+#[post("/media/files/<name>", data = "<data>")]
+fn upload_file(name: String, data: Data) -> ApiResult<Json<MediaList>> {
+    let path = image_path(&name)?;
+    fs::create_dir_all(media_dir())?;
+    // Upload to a hidden file first, so a failed upload doesn't replace
+    // the image that's there.
+    let partial = media_dir().join(format!(".{}.upload", name));
+    let copied = File::create(&partial).and_then(|mut file| {
+        io::copy(&mut data.open().take(UPLOAD_LIMIT + 1), &mut file)
+    });
+    match copied {
+        Ok(len) if len > UPLOAD_LIMIT => {
+            let _ = fs::remove_file(&partial);
+            return Err(ApiError::new(Status::PayloadTooLarge, "too_large",
+                                     format!("Images can't be over {} bytes", UPLOAD_LIMIT)));
+        },
+        Ok(_) => fs::rename(&partial, path)?,
+        Err(e) => {
+            let _ = fs::remove_file(&partial);
+            return Err(ApiError::from(e));
+        },
+    }
+    Ok(Json(list_media()?))
+}
+
+#[delete("/media/files/<name>")]
+fn delete_file(name: String) -> ApiResult<Json<MediaList>> {
+    fs::remove_file(image_path(&name)?)?;
+    Ok(Json(list_media()?))
+}
+
+#[post("/media/messages", format = "application/json", data = "<messages>")]
+fn set_messages(messages: Json<Vec<String>>) -> ApiResult<Json<MediaList>> {
+    fs::create_dir_all(media_dir())?;
+    let mut file = File::create(media_dir().join(MESSAGES_FILE))?;
+    serde_json::to_writer(&mut file, &messages.0).map_err(|e| {
+        io::Error::new(io::ErrorKind::Other, e)
+    })?;
+    file.flush()?;
+    Ok(Json(list_media()?))
+}
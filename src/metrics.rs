@@ -0,0 +1,190 @@
+/*! Operational metrics, served at `/metrics` in the Prometheus text
+    format. We keep track of:
+
+    * request counts and latencies per route, via a fairing,
+    * how long requests and the tick thread wait for the game state lock,
+    * how far the tick thread strays from its intended interval, and
+    * how many display clients have polled for updates recently.
+ */
+
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use rocket::{Data, Request, Response};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::response::content;
+
+/// Upper bounds of the request latency histogram buckets, in seconds.
+const BUCKETS: [f64; 9] = [0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0];
+/// A display that hasn't polled for this long is counted as gone.
+const CLIENT_TIMEOUT: u64 = 5;
+/// The routes that displays poll for updates.
+const DISPLAY_PATHS: [&'static str; 4] =
+    ["/score/update", "/overlay/update", "/scoresheet/update", "/penalties/"];
+
+fn as_secs_f64(d: Duration) -> f64 {
+    d.as_secs() as f64 + d.subsec_nanos() as f64 / 1e9
+}
+
+#[derive(Default)]
+struct Histogram {
+    buckets: [u64; 9],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, secs: f64) {
+        for (i, &bound) in BUCKETS.iter().enumerate() {
+            if secs <= bound {
+                self.buckets[i] += 1;
+            }
+        }
+        self.sum += secs;
+        self.count += 1;
+    }
+}
+
+#[derive(Default)]
+struct Summary {
+    sum: f64,
+    count: u64,
+    max: f64,
+}
+
+impl Summary {
+    fn observe(&mut self, secs: f64) {
+        self.sum += secs;
+        self.count += 1;
+        if secs > self.max {
+            self.max = secs;
+        }
+    }
+}
+
+#[derive(Default)]
+struct Metrics {
+    /// Keyed by (method, route).
+    requests: BTreeMap<(String, String), Histogram>,
+    lock_wait: BTreeMap<&'static str, Summary>,
+    tick_jitter: Summary,
+    clients: HashMap<IpAddr, Instant>,
+}
+
+thread_local! {
+    /// When the request being handled on this thread started. Rocket runs
+    /// the request fairing, the handler and the response fairing on the
+    /// same thread.
+    static REQUEST_START: Cell<Option<Instant>> = Cell::new(None);
+}
+
+/// The fairing that records request counts and latencies.
+pub struct RequestMetrics;
+
+impl Fairing for RequestMetrics {
+    fn info(&self) -> Info {
+        Info { name: "Request metrics", kind: Kind::Request | Kind::Response }
+    }
+
+    fn on_request(&self, request: &mut Request, _: &Data) {
+        REQUEST_START.with(|s| s.set(Some(Instant::now())));
+        let path = request.uri().path();
+        if DISPLAY_PATHS.iter().any(|p| path.starts_with(p)) {
+            if let Some(addr) = request.remote() {
+                METRICS.lock().unwrap().clients.insert(addr.ip(), Instant::now());
+            }
+        }
+    }
+
+    fn on_response(&self, request: &Request, _: &mut Response) {
+        let start = match REQUEST_START.with(|s| s.replace(None)) {
+            Some(s) => s,
+            None => return,
+        };
+        let route = match request.route() {
+            Some(r) => r.uri.as_str().to_string(),
+            None => String::from("unmatched"),
+        };
+        let key = (format!("{}", request.method()), route);
+        let mut metrics = METRICS.lock().unwrap();
+        metrics.requests.entry(key).or_insert_with(Histogram::default)
+            .observe(as_secs_f64(start.elapsed()));
+    }
+}
+
+/// Record how long someone waited to take the game state lock.
+pub fn record_lock_wait(kind: &'static str, wait: Duration) {
+    METRICS.lock().unwrap().lock_wait.entry(kind).or_insert_with(Summary::default)
+        .observe(as_secs_f64(wait));
+}
+
+/// Record how far one tick's interval was from the intended one.
+pub fn record_tick(interval: Duration, intended: Duration) {
+    let jitter = if interval > intended { interval - intended } else { intended - interval };
+    METRICS.lock().unwrap().tick_jitter.observe(as_secs_f64(jitter));
+}
+
+/// Write a summary family, and the largest value seen as a gauge family of
+/// its own, since a summary can't carry one. `name` ends in `_seconds`.
+fn write_summaries(out: &mut String, name: &str, help: &str, series: &[(String, &Summary)]) {
+    writeln!(out, "# HELP {} {}", name, help).unwrap();
+    writeln!(out, "# TYPE {} summary", name).unwrap();
+    for &(ref labels, s) in series {
+        writeln!(out, "{}_sum{} {}", name, labels, s.sum).unwrap();
+        writeln!(out, "{}_count{} {}", name, labels, s.count).unwrap();
+    }
+    let max_name = format!("{}_max_seconds", name.trim_right_matches("_seconds"));
+    writeln!(out, "# HELP {} Largest value of {}.", max_name, name).unwrap();
+    writeln!(out, "# TYPE {} gauge", max_name).unwrap();
+    for &(ref labels, s) in series {
+        writeln!(out, "{}{} {}", max_name, labels, s.max).unwrap();
+    }
+}
+
+#[get("/metrics")]
+fn get_metrics() -> content::Plain<String> {
+    let mut metrics = METRICS.lock().unwrap();
+    let mut out = String::new();
+
+    writeln!(out, "# HELP derbyboard_request_duration_seconds Request latency by route.").unwrap();
+    writeln!(out, "# TYPE derbyboard_request_duration_seconds histogram").unwrap();
+    for (&(ref method, ref route), h) in &metrics.requests {
+        let labels = format!("method=\"{}\",route=\"{}\"", method, route);
+        for (i, bound) in BUCKETS.iter().enumerate() {
+            writeln!(out, "derbyboard_request_duration_seconds_bucket{{{},le=\"{}\"}} {}",
+                     labels, bound, h.buckets[i]).unwrap();
+        }
+        writeln!(out, "derbyboard_request_duration_seconds_bucket{{{},le=\"+Inf\"}} {}",
+                 labels, h.count).unwrap();
+        writeln!(out, "derbyboard_request_duration_seconds_sum{{{}}} {}", labels, h.sum).unwrap();
+        writeln!(out, "derbyboard_request_duration_seconds_count{{{}}} {}", labels, h.count).unwrap();
+    }
+
+    {
+        let lock_wait = metrics.lock_wait.iter()
+            .map(|(kind, s)| (format!("{{kind=\"{}\"}}", kind), s)).collect::<Vec<_>>();
+        write_summaries(&mut out, "derbyboard_game_lock_wait_seconds",
+                        "Time spent waiting for the game state lock.", &lock_wait);
+    }
+
+    write_summaries(&mut out, "derbyboard_tick_jitter_seconds",
+                    "Deviation of the tick thread from its interval.",
+                    &[(String::new(), &metrics.tick_jitter)]);
+
+    let timeout = Duration::new(CLIENT_TIMEOUT, 0);
+    metrics.clients.retain(|_, seen| seen.elapsed() < timeout);
+    writeln!(out, "# HELP derbyboard_display_clients Clients that polled for updates in the last {} seconds.",
+             CLIENT_TIMEOUT).unwrap();
+    writeln!(out, "# TYPE derbyboard_display_clients gauge").unwrap();
+    writeln!(out, "derbyboard_display_clients {}", metrics.clients.len()).unwrap();
+
+    content::Plain(out)
+}
+
+lazy_static! {
+    static ref METRICS: Mutex<Metrics> = Mutex::new(Metrics::default());
+}
@@ -4,6 +4,7 @@ function send_command(update, callback) {
     // TODO: debounce enable
     xhr.open('POST', "/score/update");
     xhr.setRequestHeader('Content-Type', 'application/json');
+    OfficialSession.sign(xhr);
     xhr.onreadystatechange = function () {
         if (xhr.readyState === XMLHttpRequest.DONE) {
             if (xhr.status !== 200) {
@@ -39,47 +40,44 @@ function format_time(time) {
 
 function updateClock(data) {
     var label = 'none';
-    var clock = '0:00';
     for (var l in data) {
         switch (l) {
         case 'timeout':
-            label = 'Official Timeout';
-            clock = data.timeout;
+            label = I18n.t('Official Timeout');
             break;
         case 'team_timeout':
-            label = 'Team Timeout';
-            clock = data.team_timeout[1];
+            label = I18n.t('Team Timeout');
             break;
         case 'review':
-            label = 'Official Review';
-            clock = data.review[1];
+            label = I18n.t('Official Review');
             break;
         case 'jam':
-            label = 'Jam ' + data.jam[0];
-            clock = data.jam[1];
+            label = I18n.t('Jam {0}', data.jam[0]);
             break;
         case 'lineup':
-            label = 'Lineup';
-            clock = data.lineup;
+            label = I18n.t('Lineup');
             break;
         case 'intermission':
-            label = 'Halftime';
-            clock = data.intermission;
+            label = I18n.t('Halftime');
             break;
         case 'time_to_derby':
-            label = "Time To Derby";
-            clock = data.time_to_derby;
+            label = I18n.t('Time To Derby');
             break;
         }
     }
     setInner('clocklabel', label);
-    setInner('clocktime', format_time(clock.secs));
+}
+
+function renderClocks() {
+    if (!ClockSync.last) return;
+    setInner('periodtime', format_time((ClockSync.gameclock() / 1000)|0));
+    setInner('clocktime', format_time((ClockSync.activeclock() / 1000)|0));
 }
 
 function updateScore(data) {
-    setInner('periodnum', 'Period ' + data.gameclock[0]);
-    setInner('periodtime', format_time(data.gameclock[1].secs));
+    setInner('periodnum', I18n.t('Period {0}', data.gameclock[0]));
     updateClock(data.activeclock);
+    renderClocks();
     setInner('team1tos', data.timeouts[0]);
     setInner('team1ors', data.reviews[0]);
     setInner('team2tos', data.timeouts[1]);
@@ -90,6 +88,10 @@ function updateScore(data) {
     disable('team1or', data.reviews[0] < 1);
     disable('team2to', data.timeouts[1] < 1);
     disable('team2or', data.reviews[1] < 1);
+    var in_timeout = !!(data.activeclock.timeout || data.activeclock.team_timeout ||
+                        data.activeclock.review);
+    disable('endtimeout', !in_timeout);
+    document.getElementById('convert').hidden = !in_timeout;
     if (data.activeclock.review) {
         var team1review = (data.activeclock.review[0] == 'Home');
         document.getElementById('review1retain').disabled = team1review;
@@ -106,10 +108,14 @@ function updateScore(data) {
 
 function update() {
     var xhr = new XMLHttpRequest();
+    var sent = Date.now();
     xhr.open('GET', "/score/update");
     xhr.onreadystatechange = function () {
         if(xhr.readyState == XMLHttpRequest.DONE && xhr.status == 200) {
-            updateScore(JSON.parse(xhr.responseText));
+            var data = JSON.parse(xhr.responseText);
+            ClockSync.observe(data, sent, Date.now());
+            lastData = data;
+            updateScore(data);
         }
     }
     xhr.send();
@@ -144,15 +150,106 @@ function doSetTime() {
     }
 }
 
+/* The correction fields start out with the current values; only the ones
+   the operator changed are sent. */
+var lastData = null;
+
+function fillCorrections() {
+    var data = lastData;
+    if (!data) return;
+    var jam = data.activeclock.jam ? data.activeclock.jam[0] : '';
+    var fields = {
+        fixclock: format_time((ClockSync.activeclock() / 1000)|0),
+        fixperiod: data.gameclock[0], fixjam: jam,
+        fixscore1: data.score[0], fixtos1: data.timeouts[0], fixors1: data.reviews[0],
+        fixscore2: data.score[1], fixtos2: data.timeouts[1], fixors2: data.reviews[1],
+    };
+    for (var id in fields) {
+        var input = document.getElementById(id);
+        input.value = fields[id];
+        input.dataset.orig = input.value;
+        input.classList.remove('error');
+    }
+}
+
+function changed(id) {
+    var input = document.getElementById(id);
+    return input.value !== input.dataset.orig && input.value !== '';
+}
+
+function number(id) {
+    var input = document.getElementById(id);
+    var n = /^\d+$/.test(input.value) ? parseInt(input.value) : null;
+    if (n === null) input.classList.add('error');
+    return n;
+}
+
+function applyCorrections() {
+    var cmds = [];
+    if (changed('fixclock')) {
+        var time = parseTime(document.getElementById('fixclock').value);
+        if (time === null) {
+            document.getElementById('fixclock').classList.add('error');
+            return;
+        }
+        cmds.push({set_active_time: time});
+    }
+    var simple = [['fixperiod', 'set_period'], ['fixjam', 'set_jam']];
+    var teams = [['1', 'Home'], ['2', 'Away']];
+    for (var t = 0; t < teams.length; t++) {
+        simple.push(['fixtos' + teams[t][0], 'set_timeouts', teams[t][1]]);
+        simple.push(['fixors' + teams[t][0], 'set_reviews', teams[t][1]]);
+    }
+    for (var i = 0; i < simple.length; i++) {
+        if (!changed(simple[i][0])) continue;
+        var n = number(simple[i][0]);
+        if (n === null) return;
+        var cmd = {};
+        cmd[simple[i][1]] = simple[i][2] ? [simple[i][2], n] : n;
+        cmds.push(cmd);
+    }
+    if (changed('fixscore1') || changed('fixscore2')) {
+        var s1 = number('fixscore1'), s2 = number('fixscore2');
+        if (s1 === null || s2 === null) return;
+        cmds.push({score_set: [s1, s2]});
+    }
+    cmds.forEach(function (cmd) { send_command(cmd) });
+    document.getElementById('corrections').hidden = true;
+}
+
+/* Send the time the button was pressed along with the command, so that a
+   slow network doesn't make the jam start or stop late. Until we've heard
+   from the server we don't know its clock, so just send the plain command. */
+function timed_command(name) {
+    var cmd = {};
+    if (ClockSync.last) {
+        cmd[name + '_at'] = ClockSync.now();
+    } else {
+        cmd[name] = null;
+    }
+    send_command(cmd);
+}
+
 function init() {
-    addclick('startjam', function () { send_command({start_jam: null})} );
-    addclick('stopjam', function () { send_command({stop_jam: null})} );
+    addclick('startjam', function () { timed_command('start_jam') });
+    addclick('stopjam', function () { timed_command('stop_jam') });
     addclick('timeoutbtn', function () {
         send_command({official_timeout: null})});
     addclick('team1to', function () { send_command({team_timeout: 'Home'})});
     addclick('team1or', function () { send_command({official_review: 'Home'})});
     addclick('team2to', function () { send_command({team_timeout: 'Away'})});
     addclick('team2or', function () { send_command({official_review: 'Away'})});
+    addclick('endtimeout', function () { send_command({end_timeout: null})});
+    addclick('convofficial', function () {
+        send_command({convert_timeout: ['official', null]})});
+    addclick('conv1to', function () {
+        send_command({convert_timeout: ['team', 'Home']})});
+    addclick('conv1or', function () {
+        send_command({convert_timeout: ['review', 'Home']})});
+    addclick('conv2to', function () {
+        send_command({convert_timeout: ['team', 'Away']})});
+    addclick('conv2or', function () {
+        send_command({convert_timeout: ['review', 'Away']})});
     addclick('review1retain', function () {
         send_command({review_retained: 'Home'})});
     addclick('review1lost', function () {
@@ -170,7 +267,15 @@ function init() {
         setfield.onblur = doSetTime;
     });
 
+    addclick('showcorrections', function () {
+        var panel = document.getElementById('corrections');
+        panel.hidden = !panel.hidden;
+        if (!panel.hidden) fillCorrections();
+    });
+    addclick('applycorrections', applyCorrections);
+
     window.setInterval(function () { update() }, 500);
+    window.setInterval(function () { renderClocks() }, 50);
 }
 
 init();
@@ -0,0 +1,258 @@
+/*! The officiating crew: referees and NSOs, each with the positions they
+    can work, loaded from DerbyJSON `Person`s with their `roles` and
+    `certifications`. An official working one of the pages opens an
+    operator session for themselves and a position, and sends its token
+    in the `X-Official-Session` header; everything the game does in
+    answer to their requests is then recorded against them. Sessions say
+    who is doing what, they don't keep anybody out.
+ */
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use derbyjson;
+use rand::{OsRng, Rng};
+use rocket::http::Status;
+use rocket_contrib::Json;
+use serde_json::{self, Value};
+
+use error::{ApiError, ApiResult};
+use events;
+use gamestate::ActionEntry;
+use guard::{Game, MutGame};
+use check_not_final;
+
+/// The header operator sessions are sent in.
+pub const SESSION_HEADER: &'static str = "X-Official-Session";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Position {
+    HeadReferee,
+    JamReferee,
+    InsidePackReferee,
+    OutsidePackReferee,
+    HeadNso,
+    JamTimer,
+    Scorekeeper,
+    PenaltyLineupTracker,
+    PenaltyTracker,
+    PenaltyWrangler,
+    PenaltyBoxManager,
+    PenaltyBoxTimer,
+    InsideWhiteboard,
+    LineupTracker,
+    ScoreboardOperator,
+}
+
+const POSITIONS: [(Position, &'static str, &'static str); 15] = [
+    (Position::HeadReferee, "Head Referee", "HR"),
+    (Position::JamReferee, "Jam Referee", "JR"),
+    (Position::InsidePackReferee, "Inside Pack Referee", "IPR"),
+    (Position::OutsidePackReferee, "Outside Pack Referee", "OPR"),
+    (Position::HeadNso, "Head NSO", "HNSO"),
+    (Position::JamTimer, "Jam Timer", "JT"),
+    (Position::Scorekeeper, "Scorekeeper", "SK"),
+    (Position::PenaltyLineupTracker, "Penalty Lineup Tracker", "PLT"),
+    (Position::PenaltyTracker, "Penalty Tracker", "PT"),
+    (Position::PenaltyWrangler, "Penalty Wrangler", "PW"),
+    (Position::PenaltyBoxManager, "Penalty Box Manager", "PBM"),
+    (Position::PenaltyBoxTimer, "Penalty Box Timer", "PBT"),
+    (Position::InsideWhiteboard, "Inside Whiteboard", "IWB"),
+    (Position::LineupTracker, "Lineup Tracker", "LT"),
+    (Position::ScoreboardOperator, "Scoreboard Operator", "SO"),
+];
+
+impl Position {
+    /// Read a DerbyJSON role, either spelled out or as the usual
+    /// abbreviation. Roles that aren't officiating positions, such as
+    /// `Skater`, give `None`.
+    pub fn from_role(role: &str) -> Option<Position> {
+        let role = role.trim().to_lowercase();
+        POSITIONS.iter().find(|&&(_, name, abbr)| {
+            role == name.to_lowercase() || role == abbr.to_lowercase()
+        }).map(|&(position, _, _)| position)
+    }
+    /// The role as it goes in DerbyJSON.
+    pub fn role(&self) -> &'static str {
+        POSITIONS.iter().find(|&&(p, _, _)| p == *self).unwrap().1
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct Official {
+    pub name: String,
+    pub league: Option<String>,
+    pub positions: Vec<Position>,
+    /// As they came in the DerbyJSON, since there's nothing here that
+    /// looks inside them.
+    pub certifications: Value,
+}
+
+impl Official {
+    /// The official a DerbyJSON person describes, or `None` if none of
+    /// their roles is an officiating position; a game's people can
+    /// include skaters, coaches and announcers.
+    pub fn from_derbyjson(dj_person: derbyjson::Person) -> Option<Official> {
+        let positions = dj_person.roles.iter().filter_map(|r| Position::from_role(r))
+            .collect::<Vec<_>>();
+        if positions.is_empty() {
+            return None;
+        }
+        Some(Official {
+            certifications: serde_json::to_value(&dj_person.certifications).unwrap(),
+            name: dj_person.name, league: dj_person.league, positions: positions,
+        })
+    }
+    pub fn as_derbyjson(&self) -> derbyjson::Person {
+        derbyjson::Person {
+            name: self.name.clone(),
+            roles: self.positions.iter().map(|p| String::from(p.role())).collect(),
+            certifications: serde_json::from_value(self.certifications.clone()).unwrap_or(None),
+            league: self.league.clone(),
+            number: None, insurance: None, legal: None, skated: None, uuid: None,
+        }
+    }
+}
+
+/// Who did something: an official, and the position they were working.
+#[derive(Serialize, Clone, Debug)]
+pub struct Operator {
+    pub name: String,
+    pub position: Position,
+}
+
+#[derive(Serialize, Clone)]
+struct Session {
+    token: String,
+    operator: Operator,
+    /// When the session was opened, in milliseconds since the epoch.
+    started: u64,
+}
+
+/// An open session as anyone can see it: without the token, which is
+/// only given to whoever opened it.
+#[derive(Serialize)]
+struct SessionInfo {
+    operator: Operator,
+    started: u64,
+}
+
+/// Close every session, as when the crew or the whole game is replaced and
+/// the officials they were opened for may not be on it any more.
+pub fn close_all_sessions() {
+    SESSIONS.lock().unwrap().clear();
+}
+
+/// A new session token: 128 random bits, so that nobody can work out
+/// anybody else's.
+fn new_token() -> ApiResult<String> {
+    let mut rng = OsRng::new().map_err(|e| ApiError::new(
+        Status::InternalServerError, "no_random", format!("Can't make a session token: {}", e)))?;
+    Ok(format!("{:016x}{:016x}", rng.next_u64(), rng.next_u64()))
+}
+
+/// The operator for a session token, if it's a session that's still open.
+pub fn operator(token: &str) -> Option<Operator> {
+    SESSIONS.lock().unwrap().get(token).map(|s| s.operator.clone())
+}
+
+#[get("/officials")]
+fn get_officials(game: Game) -> Json<Vec<Official>> {
+    Json(game.officials().to_vec())
+}
+
+/// Replace the crew with a list of DerbyJSON people. People with no
+/// officiating role are left out.
+#[post("/officials", format = "application/json", data = "<persons>")]
+fn set_officials(mut game: MutGame, persons: Json<Vec<derbyjson::Person>>)
+                 -> ApiResult<Json<Vec<Official>>> {
+    check_not_final(&game)?;
+    let officials = persons.0.into_iter().filter_map(Official::from_derbyjson).collect();
+    game.set_officials(officials);
+    close_all_sessions();
+    Ok(Json(game.officials().to_vec()))
+}
+
+#[derive(Deserialize)]
+struct SessionCmd {
+    /// The official's name, as on the crew list.
+    official: String,
+    position: Position,
+}
+
+#[post("/officials/sessions", format = "application/json", data = "<cmd>")]
+fn open_session(game: Game, cmd: Json<SessionCmd>) -> ApiResult<Json<Session>> {
+    let cmd = cmd.0;
+    let official = match game.officials().iter().find(|o| o.name == cmd.official) {
+        Some(o) => o,
+        None => return Err(ApiError::not_found(
+            "no_such_official", format!("No official {:?} on the crew", cmd.official))),
+    };
+    if !official.positions.contains(&cmd.position) {
+        return Err(ApiError::bad_request(
+            "no_position", format!("{} doesn't work {}", official.name, cmd.position.role())));
+    }
+    let now = events::now_millis();
+    let session = Session {
+        token: new_token()?,
+        operator: Operator { name: official.name.clone(), position: cmd.position },
+        started: now,
+    };
+    SESSIONS.lock().unwrap().insert(session.token.clone(), session.clone());
+    Ok(Json(session))
+}
+
+#[get("/officials/sessions")]
+fn list_sessions() -> Json<Vec<SessionInfo>> {
+    let mut sessions = SESSIONS.lock().unwrap().values()
+        .map(|s| SessionInfo { operator: s.operator.clone(), started: s.started })
+        .collect::<Vec<_>>();
+    sessions.sort_by_key(|s| s.started);
+    Json(sessions)
+}
+
+#[delete("/officials/sessions/<token>")]
+fn close_session(token: String) -> ApiResult<&'static str> {
+    match SESSIONS.lock().unwrap().remove(&token) {
+        Some(_) => Ok("success"),
+        None => Err(ApiError::not_found("no_such_session", format!("No session {:?}", token))),
+    }
+}
+
+/// Everything officials have done to the game, oldest first.
+#[get("/officials/actions")]
+fn get_actions(game: Game) -> Json<Vec<ActionEntry>> {
+    Json(game.actions().to_vec())
+}
+
+lazy_static! {
+    static ref SESSIONS: Mutex<HashMap<String, Session>> = Mutex::new(HashMap::new());
+}
+
+#[cfg(test)]
+mod tests {
+    use derbyjson;
+    use super::{Official, Position};
+
+    #[test]
+    fn test_from_role() {
+        assert_eq!(Position::from_role("Jam Timer"), Some(Position::JamTimer));
+        assert_eq!(Position::from_role("plt"), Some(Position::PenaltyLineupTracker));
+        assert_eq!(Position::from_role("Skater"), None);
+        assert_eq!(Position::from_role(Position::HeadNso.role()), Some(Position::HeadNso));
+    }
+
+    #[test]
+    fn test_skip_non_officials() {
+        let person = |roles: &[&str]| derbyjson::Person {
+            name: String::from("Someone"),
+            roles: roles.iter().map(|r| String::from(*r)).collect(),
+            certifications: None, league: None,
+            number: None, insurance: None, legal: None, skated: None, uuid: None,
+        };
+        assert!(Official::from_derbyjson(person(&["Skater"])).is_none());
+        let official = Official::from_derbyjson(person(&["Skater", "JT"])).unwrap();
+        assert_eq!(official.positions, vec![Position::JamTimer]);
+    }
+}
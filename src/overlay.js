@@ -0,0 +1,97 @@
+"use strict";
+
+/* Expects an update from /overlay/update like:
+{ home: { name, color, logo, score, jamscore, lead }, away: { ... },
+  period, periodclock, clocktype, jam, clock, server_time, clocks,
+  show: { scorebug, teamnames, periodclock, jamclock, lead } }
+*/
+
+function setInner(id, content) {
+    document.getElementById(id).innerText = content;
+}
+
+function show(id, yesno) {
+    document.getElementById(id).classList.toggle('hidden', !yesno);
+}
+
+function format_time(time) {
+    var mins = (time / 60)|0;
+    var secs = (time % 60);
+    var secstr = secs < 10 ? ("0" + secs) : ("" + secs);
+    if (mins > 0) {
+        return mins + ":" + secstr;
+    } else {
+        return secstr;
+    }
+}
+
+function logoUrl(logo) {
+    if (/^https?:/.test(logo)) {
+        return logo;
+    }
+    return '/logos/' + logo.split('/').map(encodeURIComponent).join('/');
+}
+
+var clocklabels = {
+    timeout: 'Timeout',
+    team_timeout: 'Team Timeout',
+    review: 'Review',
+    lineup: 'Lineup',
+    intermission: 'Halftime',
+    time_to_derby: 'Time To Derby',
+};
+
+function updateOverlay(data) {
+    var teams = [data.home, data.away];
+    for (var i = 0; i < 2; i++) {
+        var n = i + 1;
+        setInner('name' + n, teams[i].name);
+        setInner('score' + n, teams[i].score);
+        document.getElementById('lead' + n).classList.toggle(
+            'active', data.show.lead && teams[i].lead);
+        show('name' + n, data.show.teamnames);
+        document.getElementById('name' + n).style.borderLeftColor =
+            teams[i].color || 'transparent';
+        var logo = document.getElementById('logo' + n);
+        if (teams[i].logo) {
+            var url = logoUrl(teams[i].logo);
+            if (logo.getAttribute('src') != url) logo.src = url;
+        }
+        logo.hidden = !teams[i].logo;
+    }
+    setInner('periodlabel', I18n.t('Period {0}', data.period));
+    if (data.clocktype == 'jam') {
+        setInner('jamlabel', I18n.t('Jam {0}', data.jam));
+    } else if (clocklabels[data.clocktype]) {
+        setInner('jamlabel', I18n.t(clocklabels[data.clocktype]));
+    } else {
+        setInner('jamlabel', '');
+    }
+    renderClocks();
+    show('scorebug', data.show.scorebug);
+    show('periodclock', data.show.periodclock);
+    show('jamclock', data.show.jamclock);
+}
+
+function renderClocks() {
+    if (!ClockSync.last) return;
+    setInner('periodtime', format_time((ClockSync.gameclock() / 1000)|0));
+    setInner('jamtime', format_time((ClockSync.activeclock() / 1000)|0));
+}
+
+function updater() {
+    var xhr = new XMLHttpRequest();
+    var sent = Date.now();
+    xhr.open('GET', '/overlay/update');
+    xhr.onreadystatechange = function () {
+        if (xhr.readyState === XMLHttpRequest.DONE && xhr.status === 200) {
+            var data = JSON.parse(xhr.responseText);
+            ClockSync.observe(data, sent, Date.now());
+            updateOverlay(data);
+        }
+    };
+    xhr.send();
+}
+
+window.setInterval(function(f) { updater(); }, 250);
+window.setInterval(function(f) { renderClocks(); }, 50);
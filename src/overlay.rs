@@ -0,0 +1,133 @@
+/*! The broadcast overlay. This provides a compact score feed meant to be
+    polled by a browser source in streaming software, and a small amount
+    of operator-controlled state for which elements of the overlay are
+    shown.
+ */
+
+use std::sync::RwLock;
+
+use rocket_contrib::Json;
+
+use events;
+use gamestate::{ActiveClock, ClockState};
+use gamestate::jamstate::Team;
+use guard::Game;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct OverlaySettings {
+    pub scorebug: bool,
+    pub teamnames: bool,
+    pub periodclock: bool,
+    pub jamclock: bool,
+    pub lead: bool,
+}
+
+impl Default for OverlaySettings {
+    fn default() -> OverlaySettings {
+        OverlaySettings {
+            scorebug: true, teamnames: true, periodclock: true,
+            jamclock: true, lead: true,
+        }
+    }
+}
+
+/// A partial update to the overlay settings. Fields that are left out are
+/// not changed.
+#[derive(Deserialize)]
+struct OverlayControl {
+    scorebug: Option<bool>,
+    teamnames: Option<bool>,
+    periodclock: Option<bool>,
+    jamclock: Option<bool>,
+    lead: Option<bool>,
+}
+
+#[derive(Serialize)]
+struct OverlayTeam {
+    name: String,
+    color: Option<String>,
+    logo: Option<String>,
+    score: u32,
+    jamscore: u32,
+    lead: bool,
+}
+
+#[derive(Serialize)]
+struct OverlayUpdate {
+    home: OverlayTeam,
+    away: OverlayTeam,
+    period: u8,
+    periodclock: u64,
+    clocktype: &'static str,
+    jam: u8,
+    clock: u64,
+    server_time: u64,
+    clocks: ClockState,
+    show: OverlaySettings,
+}
+
+fn clock_summary(clock: ActiveClock) -> (&'static str, u8, u64) {
+    match clock {
+        ActiveClock::timeout(d) => ("timeout", 0, d.as_secs()),
+        ActiveClock::team_timeout(_, d) => ("team_timeout", 0, d.as_secs()),
+        ActiveClock::review(_, d) => ("review", 0, d.as_secs()),
+        ActiveClock::jam(n, d) => ("jam", n, d.as_secs()),
+        ActiveClock::lineup(d) => ("lineup", 0, d.as_secs()),
+        ActiveClock::time_to_derby(d) => ("time_to_derby", 0, d.as_secs()),
+        ActiveClock::intermission(d) => ("intermission", 0, d.as_secs()),
+        ActiveClock::none => ("none", 0, 0),
+    }
+}
+
+#[get("/overlay/update")]
+fn overlay_update(game: Game) -> Json<OverlayUpdate> {
+    let score = game.total_score();
+    let jam = if game.cur_jam().played() {
+        Some(game.cur_jam())
+    } else {
+        game.prev_jam()
+    };
+    let jamscore = jam.map_or((0, 0), |j| j.jam_score());
+    let lead = |team: Team| jam.map_or(false, |j| j[team].lead());
+    let (period, periodclock) = game.get_time();
+    let (clocktype, jamnum, clock) = clock_summary(game.get_active_clock());
+
+    Json(OverlayUpdate {
+        home: OverlayTeam {
+            name: game.roster(Team::Home).name.clone(),
+            color: game.roster(Team::Home).color.clone(),
+            logo: game.roster(Team::Home).logo.clone(),
+            score: score.0, jamscore: jamscore.0, lead: lead(Team::Home),
+        },
+        away: OverlayTeam {
+            name: game.roster(Team::Away).name.clone(),
+            color: game.roster(Team::Away).color.clone(),
+            logo: game.roster(Team::Away).logo.clone(),
+            score: score.1, jamscore: jamscore.1, lead: lead(Team::Away),
+        },
+        period: period, periodclock: periodclock.as_secs(),
+        clocktype: clocktype, jam: jamnum, clock: clock,
+        server_time: events::now_millis(), clocks: game.clock_state(),
+        show: SETTINGS.read().unwrap().clone(),
+    })
+}
+
+#[get("/overlay/control")]
+fn get_control() -> Json<OverlaySettings> {
+    Json(SETTINGS.read().unwrap().clone())
+}
+
+#[post("/overlay/control", format = "application/json", data = "<cmd>")]
+fn post_control(cmd: Json<OverlayControl>) -> Json<OverlaySettings> {
+    let mut settings = SETTINGS.write().unwrap();
+    if let Some(v) = cmd.scorebug { settings.scorebug = v }
+    if let Some(v) = cmd.teamnames { settings.teamnames = v }
+    if let Some(v) = cmd.periodclock { settings.periodclock = v }
+    if let Some(v) = cmd.jamclock { settings.jamclock = v }
+    if let Some(v) = cmd.lead { settings.lead = v }
+    Json(settings.clone())
+}
+
+lazy_static! {
+    static ref SETTINGS: RwLock<OverlaySettings> = RwLock::new(OverlaySettings::default());
+}
@@ -121,13 +121,18 @@ function penalty(team, skater, penaltycode) {
     var xhr = new XMLHttpRequest();
     xhr.open('POST', document.location + '/' + team);
     xhr.setRequestHeader('Content-Type', 'application/json');
+    OfficialSession.sign(xhr);
     xhr.onreadystatechange = function () {
         if (xhr.readyState == XMLHttpRequest.DONE && xhr.status == 200) {
             var resp = JSON.parse(xhr.responseText);
             update_board(team, resp);
         }
     };
-    xhr.send(JSON.stringify({skater: skater, code: penaltycode}));
+    var cmd = {skater: skater, code: penaltycode};
+    // When fixing up a finished game, penalties can go in an earlier jam.
+    var jam = parseInt(document.getElementById('penaltyjam').value);
+    if (jam > 0) cmd.jam = jam;
+    xhr.send(JSON.stringify(cmd));
 }
 
 function update(team) {
@@ -0,0 +1,234 @@
+/*! Printable paperwork for sanctioned games: score sheets, penalty
+    tracking and lineup sheets for each team, and a summary with the
+    timeouts and reviews. They're plain HTML laid out for printing, one
+    sheet per page, so a browser's print or save-as-PDF is all that's
+    needed.
+ */
+
+use std::cmp::min;
+
+use handlebars;
+use rocket::response::content;
+
+use events::TimeoutKind;
+use gamestate::GameState;
+use gamestate::igrf::Igrf;
+use gamestate::jamstate::{Team, TeamJamState, LINEUP_SLOTS};
+use guard::Game;
+use staticpages;
+
+/// Trip columns on the score sheet, for trips 2 to 10.
+const TRIP_COLUMNS: usize = 9;
+/// Penalty columns on the penalty tracking sheet.
+const PENALTY_COLUMNS: usize = 9;
+
+#[derive(Serialize)]
+struct ScoreRow {
+    jam: String,
+    jammer: String,
+    lost: bool,
+    lead: bool,
+    call: bool,
+    no_initial: bool,
+    trips: Vec<String>,
+    jam_total: String,
+    game_total: String,
+}
+
+#[derive(Serialize)]
+struct PenaltyCell {
+    code: String,
+    jam: String,
+}
+
+#[derive(Serialize)]
+struct PenaltyRow {
+    number: String,
+    penalties: Vec<PenaltyCell>,
+    foul_out: bool,
+    total: usize,
+}
+
+#[derive(Serialize)]
+struct LineupCell {
+    number: String,
+    /// The box trip marks, like `/ X`.
+    boxtrips: String,
+}
+
+#[derive(Serialize)]
+struct LineupRow {
+    jam: u8,
+    no_pivot: bool,
+    /// Jammer, pivot (or fourth blocker) and blockers.
+    skaters: Vec<LineupCell>,
+}
+
+#[derive(Serialize)]
+struct PeriodSheets {
+    period: u8,
+    score: Vec<ScoreRow>,
+    lineups: Vec<LineupRow>,
+    points: u32,
+}
+
+#[derive(Serialize)]
+struct TeamReport {
+    name: String,
+    color: Option<String>,
+    score: u32,
+    periods: Vec<PeriodSheets>,
+    penalties: Vec<PenaltyRow>,
+    timeouts_left: u8,
+    reviews_left: u8,
+}
+
+#[derive(Serialize)]
+struct TimeoutRow {
+    period: u8,
+    after_jam: u8,
+    kind: &'static str,
+    team: String,
+}
+
+#[derive(Serialize)]
+struct Report {
+    igrf: Igrf,
+    teams: Vec<TeamReport>,
+    timeouts: Vec<TimeoutRow>,
+}
+
+fn number(game: &GameState, team: Team, idx: Option<usize>) -> String {
+    idx.and_then(|i| game.roster(team).skaters.get(i))
+        .map_or(String::new(), |s| s.number.clone())
+}
+
+/// Lay out trip points in the trip columns, starting at column `from`.
+/// Trips past the last column share it, as `4+4`, as they would on paper.
+fn trip_cells(from: usize, trips: &[u8]) -> Vec<String> {
+    let mut cells = vec![String::new(); TRIP_COLUMNS];
+    for (i, points) in trips.iter().enumerate() {
+        let cell = &mut cells[min(from + i, TRIP_COLUMNS - 1)];
+        if !cell.is_empty() {
+            cell.push('+');
+        }
+        cell.push_str(&points.to_string());
+    }
+    cells
+}
+
+fn score_rows(game: &GameState, team: Team, jamnum: u8, tj: &TeamJamState,
+              running: &mut u32) -> Vec<ScoreRow> {
+    let jam_total = tj.points();
+    *running += jam_total;
+    let mut rows = vec![ScoreRow {
+        jam: jamnum.to_string(), jammer: number(game, team, tj.jammer()),
+        lost: tj.lost(), lead: tj.lead(), call: tj.call(),
+        no_initial: tj.jammer_trips().is_empty() && tj.pivot_trips().is_empty(),
+        trips: trip_cells(0, tj.jammer_trips()),
+        jam_total: jam_total.to_string(), game_total: running.to_string(),
+    }];
+    if tj.starpass() {
+        // The totals go on the star pass line, as the jam isn't over
+        // until then.
+        rows[0].jam_total = String::new();
+        rows[0].game_total = String::new();
+        rows.push(ScoreRow {
+            jam: String::from("SP"), jammer: number(game, team, tj.pivot()),
+            lost: false, lead: false, call: tj.call(), no_initial: false,
+            trips: trip_cells(tj.jammer_trips().len(), tj.pivot_trips()),
+            jam_total: jam_total.to_string(), game_total: running.to_string(),
+        });
+    }
+    rows
+}
+
+fn lineup_row(game: &GameState, team: Team, jamnum: u8, tj: &TeamJamState) -> LineupRow {
+    LineupRow {
+        jam: jamnum, no_pivot: tj.no_pivot(),
+        skaters: (0..LINEUP_SLOTS).map(|slot| LineupCell {
+            number: number(game, team, tj.lineup_slot(slot)),
+            boxtrips: tj.box_trips(slot).iter().map(|m| m.as_str()).collect::<Vec<_>>().join(" "),
+        }).collect(),
+    }
+}
+
+fn team_report(game: &GameState, team: Team) -> TeamReport {
+    let mut periods: Vec<PeriodSheets> = Vec::new();
+    let mut running = 0;
+    for (idx, jam) in game.jams().iter().enumerate().filter(|&(_, j)| j.played()) {
+        let (period, jamnum) = game.jamidx_to_periodjam(idx);
+        if periods.last().map_or(true, |p| p.period != period) {
+            periods.push(PeriodSheets { period: period, score: Vec::new(),
+                                        lineups: Vec::new(), points: 0 });
+        }
+        let sheets = periods.last_mut().unwrap();
+        let tj = &jam[team];
+        sheets.points += tj.points();
+        sheets.score.extend(score_rows(game, team, jamnum, tj, &mut running));
+        sheets.lineups.push(lineup_row(game, team, jamnum, tj));
+    }
+
+    let mut by_skater = game.team_penalties(team);
+    let roster = game.roster(team);
+    let penalties = roster.skaters.iter().map(|s| {
+        let list = by_skater.remove(&s.number).unwrap_or_default();
+        let mut cells = list.iter().take(PENALTY_COLUMNS).map(|p| PenaltyCell {
+            code: p.code().to_string(),
+            jam: format!("{}{}", if p.jam().0 == 2 { "*" } else { "" }, p.jam().1),
+        }).collect::<Vec<_>>();
+        while cells.len() < PENALTY_COLUMNS {
+            cells.push(PenaltyCell { code: String::new(), jam: String::new() });
+        }
+        PenaltyRow { number: s.number.clone(), penalties: cells,
+                     foul_out: list.len() >= game.rules().foul_out as usize, total: list.len() }
+    }).collect();
+
+    let pick8 = |pair: (u8, u8)| if team == Team::Home { pair.0 } else { pair.1 };
+    TeamReport {
+        name: roster.name.clone(), color: roster.color.clone(),
+        score: if team == Team::Home { game.total_score().0 } else { game.total_score().1 },
+        periods: periods, penalties: penalties,
+        timeouts_left: pick8(game.timeouts()), reviews_left: pick8(game.reviews()),
+    }
+}
+
+fn report(game: &GameState) -> Report {
+    let timeouts = game.timeout_log().iter().map(|t| TimeoutRow {
+        period: t.period, after_jam: t.after_jam,
+        kind: match t.kind {
+            TimeoutKind::Team => "Team timeout",
+            TimeoutKind::Official => "Official timeout",
+            TimeoutKind::Review => "Official review",
+        },
+        team: t.team.map_or(String::new(), |team| game.roster(team).name.clone()),
+    }).collect();
+    Report {
+        igrf: game.igrf().clone(),
+        teams: vec![team_report(game, Team::Home), team_report(game, Team::Away)],
+        timeouts: timeouts,
+    }
+}
+
+/// Render the printable report for a game.
+pub fn render(game: &GameState) -> Result<content::Html<String>, handlebars::RenderError> {
+    staticpages::render("report", &report(game))
+}
+
+#[get("/report")]
+fn game_report(game: Game) -> Result<content::Html<String>, handlebars::RenderError> {
+    render(&game)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::trip_cells;
+
+    #[test]
+    fn test_trip_overflow() {
+        let cells = trip_cells(7, &[4, 4, 3, 1]);
+        assert_eq!(cells.len(), 9);
+        assert_eq!(cells[7], "4");
+        assert_eq!(cells[8], "4+3+1");
+    }
+}
@@ -7,6 +7,8 @@ use std::path::Path;
 use derbyjson;
 use serde_json;
 
+use config;
+
 #[derive(Clone, Serialize)]
 pub struct Skater {
     pub number: String,
@@ -41,9 +43,29 @@ impl Skater {
 pub struct Team {
     pub name: String,
     pub skaters: Vec<Skater>,
+    /// A CSS colour for the team's jerseys.
+    pub color: Option<String>,
+    /// Either a URL or a file name relative to the roster directory.
+    pub logo: Option<String>,
+}
+
+/// Check that a colour is safe to drop into a page's CSS: either a hex
+/// colour or a plain colour name.
+pub fn valid_color(color: &str) -> bool {
+    let hex = color.starts_with('#') && color.len() > 1 &&
+        color[1..].chars().all(|c| c.is_digit(16));
+    let name = color.len() > 0 && color.chars().all(|c| c.is_ascii_alphabetic());
+    hex || name
 }
 
 impl Team {
+    pub fn new(name: String) -> Team {
+        Team { name: name, skaters: Vec::new(), color: None, logo: None }
+    }
+    /// Find a skater by number. Skaters are kept sorted by number.
+    pub fn skater_index(&self, number: &str) -> Option<usize> {
+        self.skaters.binary_search_by_key(&number, |s| &*s.number).ok()
+    }
     fn from_file<R>(mut input: R)
                     -> io::Result<Team> where R : BufRead
     {
@@ -57,7 +79,7 @@ impl Team {
             Ok(_) => teamname.pop(),
             Err(e) => return Err(e),
         };
-        let mut ret = Team { name: teamname, skaters: Vec::new() };
+        let mut ret = Team::new(teamname);
         for l in input.split(b'\n') {
             let line = l?;
             let mut items = line.splitn(2, |c| *c == b'\t');
@@ -83,7 +105,7 @@ impl Team {
         ret.skaters.sort_by(|k1, k2| k1.number.cmp(&k2.number));
         Ok(ret)
     }
-    fn from_derbyjson(dj_team: derbyjson::Team) -> Team {
+    pub fn from_derbyjson(dj_team: derbyjson::Team) -> Team {
         let name = if let Some(league) = dj_team.league {
             format!("{} - {}", league, dj_team.name)
         } else {
@@ -99,54 +121,93 @@ impl Team {
             }
         }).collect::<Vec<Skater>>();
         skaters.sort_by(|k1, k2| k1.number.cmp(&k2.number));
-        Team { name: name, skaters: skaters }
+        let color = match dj_team.color {
+            Some(ref c) if !valid_color(c) => {
+                println!("Ignoring bad colour {:?} for team {}", c, name);
+                None
+            },
+            c => c,
+        };
+        Team { name: name, skaters: skaters, color: color, logo: dj_team.logo }
     }
-    fn as_derbyjson(&self) -> derbyjson::Team {
+    pub fn as_derbyjson(&self) -> derbyjson::Team {
         let dj_skaters = self.skaters.iter().map(|s| s.as_derbyjson());
         derbyjson::Team {
             name: self.name.clone(),
             persons: dj_skaters.collect(),
             league: None, abbreviation: None, level: None,
-            date: None, color: None, logo: None,
+            date: None, color: self.color.clone(), logo: self.logo.clone(),
         }
     }
 }
 
-fn load_roster_json<R>(mut input: R) -> Result<Vec<Team>, derbyjson::Error>
+fn load_roster_json<R>(mut input: R) -> Result<Vec<(String, Team)>, derbyjson::Error>
     where R : io::Read
 {
     let dj = derbyjson::load_roster(input)?;
     Ok(dj.teams.into_iter().map(
-        |(id, dj_team)| Team::from_derbyjson(dj_team)).collect())
+        |(id, dj_team)| (id, Team::from_derbyjson(dj_team))).collect())
 }
 
-fn load_one_roster(path: &Path) -> Option<Vec<Team>> {
+/// The teams in one roster file, with their ids. A team's id is the file
+/// name without its extension, followed by `:` and the team's key when a
+/// DerbyJSON file holds more than one team.
+fn load_one_roster(path: &Path) -> Option<Vec<(String, Team)>> {
+    let stem = match path.file_stem().and_then(|s| s.to_str()) {
+        Some(s) if !s.starts_with('.') => s,
+        _ => return None,
+    };
     match path.extension() {
         Some(e) if e == "json" => {
             let reader = ::std::fs::File::open(path).ok();
             if let Some(r) = reader {
-                load_roster_json(r).ok()
+                load_roster_json(r).ok().map(|teams| {
+                    if teams.len() == 1 {
+                        teams.into_iter().map(|(_, t)| (String::from(stem), t)).collect()
+                    } else {
+                        teams.into_iter().map(|(k, t)| (format!("{}:{}", stem, k), t))
+                            .collect()
+                    }
+                })
             } else { None }
         },
         Some(e) if e == "txt" => {
             let file = ::std::fs::File::open(path).ok();
             if let Some(f) = file {
                 let reader = io::BufReader::new(f);
-                Team::from_file(reader).ok().map(|x| vec!(x))
+                Team::from_file(reader).ok().map(|x| vec!((String::from(stem), x)))
             } else { None }
         },
         _ => None
     }
 }
 
+/// Load the team with the id `name`, as given by `list_rosters`, reading
+/// only the file it comes from. An empty name gives an empty team called
+/// `defname`.
 pub fn get_team(name: &str, defname: String) -> io::Result<Team> {
     if name == "" {
-        return Ok(Team { name: defname, skaters: vec!() });
+        return Ok(Team::new(defname));
+    }
+    let not_found = || io::Error::new(io::ErrorKind::NotFound,
+                                      format!("No roster {:?}", name));
+    let stem = name.splitn(2, ':').next().unwrap();
+    if stem.is_empty() || stem.starts_with('.') || stem.contains('/') || stem.contains('\\') {
+        return Err(not_found());
+    }
+    let dir = config::get().roster_dir.clone();
+    for ext in &["json", "txt"] {
+        let path = dir.join(format!("{}.{}", stem, ext));
+        if let Some(teams) = load_one_roster(path.as_path()) {
+            if let Some((_, team)) = teams.into_iter().find(|&(ref id, _)| id == name) {
+                return Ok(team);
+            }
+        }
     }
-    unimplemented!();
+    Err(not_found())
 }
 
-pub fn load_rosters(rosterdir: &OsStr) -> io::Result<Vec<Team>> {
+pub fn load_rosters(rosterdir: &OsStr) -> io::Result<Vec<(String, Team)>> {
     let mut rosters = Vec::new();
     for entry in read_dir(rosterdir)? {
         let path = entry?.path();
@@ -166,13 +227,23 @@ pub fn save_roster_json<W>(mut output: W, rosters: &[Team])
     serde_json::to_writer(&mut output, &dj_root)
 }
 
+/// All the rosters in the roster directory, keyed by an id that can be
+/// passed to `get_team`.
 pub fn list_rosters() -> HashMap<String, Team> {
-    return HashMap::new();
-
+    let dir = config::get().roster_dir.clone();
+    if !dir.is_dir() {
+        return HashMap::new();
+    }
+    match load_rosters(dir.as_os_str()) {
+        Ok(rosters) => rosters.into_iter().collect(),
+        Err(e) => {
+            println!("Error loading rosters from {}: {}", dir.display(), e);
+            HashMap::new()
+        }
+    }
 }
 
 
-
 #[cfg(test)]
 mod tests {
     use std::io::Cursor;
@@ -186,4 +257,12 @@ mod tests {
         assert_eq!(team.name, "Toaster City");
         assert_eq!(team.skaters[0].name, "Bob Rodney");
     }
+
+    #[test]
+    fn test_valid_color() {
+        assert!(super::valid_color("#ff8800"));
+        assert!(super::valid_color("teal"));
+        assert!(!super::valid_color("red;}"));
+        assert!(!super::valid_color("röd"));
+    }
 }
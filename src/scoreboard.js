@@ -18,43 +18,99 @@ function format_time(time) {
     }
 }
 
+/* During intermission and time to derby, the team boxes are replaced with
+   a rotating slideshow of sponsor images and messages. */
+var slideshow = { active: false, slides: [], current: 0, timer: null };
+
+function loadSlides() {
+    var xhr = new XMLHttpRequest();
+    xhr.open('GET', '/media/list');
+    xhr.onreadystatechange = function () {
+        if (xhr.readyState === XMLHttpRequest.DONE && xhr.status === 200) {
+            var media = JSON.parse(xhr.responseText);
+            var slides = media.images.map(function (name) {
+                return { image: '/media/files/' + encodeURIComponent(name) };
+            });
+            media.messages.forEach(function (msg) {
+                slides.push({ message: msg });
+            });
+            slideshow.slides = slides;
+        }
+    };
+    xhr.send();
+}
+
+function nextSlide() {
+    var box = document.getElementById('sponsorbox');
+    var slides = slideshow.slides;
+    var show = slideshow.active && slides.length > 0;
+    box.classList.toggle('hidden', !show);
+    document.getElementById('topdiv').classList.toggle('hidden', show);
+    if (!show) return;
+
+    slideshow.current = (slideshow.current + 1) % slides.length;
+    var slide = slides[slideshow.current];
+    box.innerHTML = '';
+    if (slide.image) {
+        var img = document.createElement('img');
+        img.src = slide.image;
+        box.appendChild(img);
+    } else {
+        box.innerText = slide.message;
+    }
+}
+
+function setSlideshow(active) {
+    if (active == slideshow.active) return;
+    slideshow.active = active;
+    if (active) {
+        loadSlides();
+        slideshow.timer = window.setInterval(function () {
+            loadSlides();
+        }, 30000);
+    } else {
+        window.clearInterval(slideshow.timer);
+    }
+    nextSlide();
+}
+
+window.setInterval(function () { nextSlide(); }, 10000);
+
 function updateClock(data) {
-    var label = 'Jam 1';
-    var clock = '2:00';
+    var label = I18n.t('Jam {0}', 1);
+    setSlideshow('intermission' in data || 'time_to_derby' in data);
     for (var l in data) {
         switch (l) {
         case 'timeout':
-            label = 'Official Timeout';
-            clock = data.timeout;
+            label = I18n.t('Official Timeout');
             break;
         case 'team_timeout':
-            label = 'Team Timeout';
-            clock = data.team_timeout[1];
+            label = I18n.t('Team Timeout');
             break;
         case 'review':
-            label = 'Official Review';
-            clock = data.review[1];
+            label = I18n.t('Official Review');
             break;
         case 'jam':
-            label = 'Jam ' + data.jam[0];
-            clock = data.jam[1];
+            label = I18n.t('Jam {0}', data.jam[0]);
             break;
         case 'lineup':
-            label = 'Lineup';
-            clock = data.lineup;
+            label = I18n.t('Lineup');
             break;
         case 'intermission':
-            label = 'Halftime';
-            clock = data.intermission;
+            label = I18n.t('Halftime');
             break;
         case 'time_to_derby':
-            label = "Time To Derby";
-            clock = data.time_to_derby;
+            label = I18n.t('Time To Derby');
             break;
         }
     }
     setInner('aclabel', label);
-    setInner('actime', format_time(clock.secs));
+}
+
+function renderClocks() {
+    if (!ClockSync.last) return;
+    setInner('periodtime', format_time((ClockSync.gameclock() / 1000)|0));
+    setInner('actime', format_time((ClockSync.activeclock() / 1000)|0));
 }
 
 function updateScore(data) {
@@ -62,9 +118,9 @@ function updateScore(data) {
     setInner('totalscore2', data.score[1]);
     setInner('jamscore1', data.jamscore[0]);
     setInner('jamscore2', data.jamscore[1]);
-    setInner('periodtime', format_time(data.gameclock[1].secs));
-    setInner('ptlabel', "Period " + data.gameclock[0]);
+    setInner('ptlabel', I18n.t('Period {0}', data.gameclock[0]));
     updateClock(data.activeclock);
+    renderClocks();
     setTimeouts(data.timeouts, data.reviews, data.activeclock);
 }
 function setTeamDots(teambox, timeouts, reviews, in_timeout, in_review) {
@@ -106,12 +162,43 @@ function setTimeouts(timeouts, reviews, activeclock) {
     }
 }
 
+function logoUrl(logo) {
+    if (/^https?:/.test(logo)) {
+        return logo;
+    }
+    return '/logos/' + logo.split('/').map(encodeURIComponent).join('/');
+}
+
+function loadTeam(team) {
+    var xhr = new XMLHttpRequest();
+    xhr.open('GET', '/gameroster/' + team);
+    xhr.onreadystatechange = function () {
+        if (xhr.readyState === XMLHttpRequest.DONE && xhr.status === 200) {
+            var roster = JSON.parse(xhr.responseText);
+            setInner('teamname' + team, roster.name);
+            var namebox = document.getElementById('teamname' + team).parentElement;
+            namebox.style.borderBottomColor = roster.color || 'black';
+            var logo = document.getElementById('logo' + team);
+            if (roster.logo) {
+                logo.src = logoUrl(roster.logo);
+                logo.hidden = false;
+            } else {
+                logo.hidden = true;
+            }
+        }
+    };
+    xhr.send();
+}
+
 function updater() {
     var xhr = new XMLHttpRequest();
+    var sent = Date.now();
     xhr.open('GET', document.location + "/update");
     xhr.onreadystatechange = function () {
         if(xhr.readyState === XMLHttpRequest.DONE && xhr.status === 200) {
-            updateScore(JSON.parse(xhr.responseText));
+            var data = JSON.parse(xhr.responseText);
+            ClockSync.observe(data, sent, Date.now());
+            updateScore(data);
         }
     };
     xhr.send();
@@ -121,6 +208,7 @@ function send_command(update) {
     var xhr = new XMLHttpRequest();
     xhr.open('POST', document.location + "/update");
     xhr.setRequestHeader('Content-Type', 'application/json');
+    OfficialSession.sign(xhr);
     xhr.onreadystatechange = function () {
         if(xhr.readyState === XMLHttpRequest.DONE && xhr.status !== 200) {
             
@@ -171,3 +259,8 @@ document.addEventListener('keydown', keyhandler);
 document.addEventListener('click', clickhandler);
 
 window.setInterval(function(f) { updater(); }, 500);
+window.setInterval(function(f) { renderClocks(); }, 50);
+// Pick up per-game colour overrides without needing a reload.
+window.setInterval(function(f) { loadTeam(1); loadTeam(2); }, 10000);
+loadTeam(1);
+loadTeam(2);
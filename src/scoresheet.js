@@ -2,6 +2,7 @@ function jam_command(jam, team, cmd) {
     var xhr = new XMLHttpRequest();
     xhr.open('POST', '/jam/'+jam+'/'+team+'/command');
     xhr.setRequestHeader('Content-Type', 'application/json');
+    OfficialSession.sign(xhr);
     xhr.onreadystatechange = function () {
         if (xhr.readyState == XMLHttpRequest.DONE && xhr.status == 200) {
             var resp = JSON.parse(xhr.responseText);
@@ -0,0 +1,35 @@
+"use strict";
+
+/* The official working this browser, as opened on /session. Pages that
+   change the game pass every request through OfficialSession.sign, so
+   that what they do is recorded against the official and their
+   position. */
+var OfficialSession = {
+    KEY: 'derbyboard.session',
+
+    /* The open session, as {token, name, position}, or null. */
+    current: function () {
+        try {
+            return JSON.parse(localStorage.getItem(this.KEY));
+        } catch (e) {
+            return null;
+        }
+    },
+
+    save: function (session) {
+        if (session) {
+            localStorage.setItem(this.KEY, JSON.stringify(session));
+        } else {
+            localStorage.removeItem(this.KEY);
+        }
+    },
+
+    /* Add the session header to a request, after open() and before
+       send(). */
+    sign: function (xhr) {
+        var session = this.current();
+        if (session) {
+            xhr.setRequestHeader('X-Official-Session', session.token);
+        }
+    }
+};
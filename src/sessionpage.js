@@ -0,0 +1,95 @@
+"use strict";
+
+/* Opening and closing this browser's officiating session. The crew comes
+   from /officials; picking an official lists the positions they work. */
+
+var crew = [];
+
+function showCurrent() {
+    var session = OfficialSession.current();
+    document.getElementById('current').textContent = session ?
+        I18n.t('Working as {0}, {1}', session.name, session.position.replace(/_/g, ' ')) :
+        I18n.t('No session open');
+    document.getElementById('close').disabled = !session;
+}
+
+function showPositions() {
+    var name = document.getElementById('official').value;
+    var select = document.getElementById('position');
+    select.innerHTML = '';
+    crew.filter(function (o) { return o.name === name; }).forEach(function (o) {
+        o.positions.forEach(function (p) {
+            var option = document.createElement('option');
+            option.value = p;
+            option.textContent = p.replace(/_/g, ' ');
+            select.appendChild(option);
+        });
+    });
+}
+
+function request(method, url, body, done) {
+    var xhr = new XMLHttpRequest();
+    xhr.open(method, url);
+    xhr.setRequestHeader('Content-Type', 'application/json');
+    xhr.onreadystatechange = function () {
+        if (xhr.readyState !== XMLHttpRequest.DONE) return;
+        if (xhr.status === 200) {
+            document.getElementById('status').textContent = '';
+            done(JSON.parse(xhr.responseText));
+        } else {
+            document.getElementById('status').textContent =
+                JSON.parse(xhr.responseText).message;
+        }
+    };
+    xhr.send(body === undefined ? null : JSON.stringify(body));
+}
+
+function closeSession(then) {
+    var session = OfficialSession.current();
+    OfficialSession.save(null);
+    if (!session) {
+        then();
+        return;
+    }
+    // A session the server has forgotten is as good as closed.
+    var xhr = new XMLHttpRequest();
+    xhr.open('DELETE', '/officials/sessions/' + encodeURIComponent(session.token));
+    xhr.onreadystatechange = function () {
+        if (xhr.readyState === XMLHttpRequest.DONE) then();
+    };
+    xhr.send();
+}
+
+document.getElementById('official').addEventListener('change', showPositions);
+
+document.getElementById('open').addEventListener('submit', function (e) {
+    e.preventDefault();
+    var cmd = {
+        official: document.getElementById('official').value,
+        position: document.getElementById('position').value
+    };
+    closeSession(function () {
+        request('POST', '/officials/sessions', cmd, function (session) {
+            OfficialSession.save({ token: session.token, name: session.operator.name,
+                                   position: session.operator.position });
+            showCurrent();
+        });
+    });
+});
+
+document.getElementById('close').addEventListener('click', function () {
+    closeSession(showCurrent);
+});
+
+request('GET', '/officials', undefined, function (officials) {
+    crew = officials;
+    var select = document.getElementById('official');
+    crew.forEach(function (o) {
+        var option = document.createElement('option');
+        option.value = o.name;
+        option.textContent = o.name;
+        select.appendChild(option);
+    });
+    showPositions();
+});
+showCurrent();
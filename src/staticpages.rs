@@ -1,42 +1,184 @@
-use rocket::response::content;
-use handlebars::Handlebars;
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
+
+use rocket::response::{content, NamedFile};
+use handlebars::{Handlebars, TemplateRenderError};
 use handlebars;
+use serde::Serialize;
 
-use guard::get_game;
+use config;
+use guard::{Game, get_game};
 use roster;
 use gamestate::jamstate::Team;
 
+/// Directory of user templates and assets that override the built-in
+/// pages, if one has been configured.
+fn templates_dir() -> Option<PathBuf> {
+    config::get().templates_dir.clone()
+}
+
+/// Read a file from the templates directory, if it's there.
+fn read_override(name: &str) -> Option<String> {
+    let path = match templates_dir() {
+        Some(dir) => dir.join(name),
+        None => return None,
+    };
+    let mut contents = String::new();
+    match File::open(path).and_then(|mut f| f.read_to_string(&mut contents)) {
+        Ok(_) => Some(contents),
+        Err(_) => None,
+    }
+}
+
+#[derive(Serialize)]
+struct PageGame<'a> {
+    home: &'a roster::Team,
+    away: &'a roster::Team,
+    score: (u32, u32),
+    period: u8,
+    jam: u8,
+}
+
+/// What a user template gets to see when it's rendered.
+#[derive(Serialize)]
+struct PageContext<'a> {
+    game: Option<PageGame<'a>>,
+}
+
+type PageResult = Result<content::Html<String>, TemplateRenderError>;
+
+/// Serve a page, rendering the user's override through Handlebars if
+/// there is one, and otherwise the built-in version.
+fn page(name: &str, builtin: &'static str) -> PageResult {
+    let source = match read_override(name) {
+        Some(s) => s,
+        None => return Ok(content::Html(String::from(builtin))),
+    };
+    let guard = get_game();
+    let context = PageContext {
+        game: guard.as_ref().map(|g| PageGame {
+            home: g.roster(Team::Home), away: g.roster(Team::Away),
+            score: g.total_score(), period: g.get_time().0, jam: g.jamnum(),
+        }),
+    };
+    Handlebars::new().template_render(&source, &context).map(|s| content::Html(s))
+}
+
+/// Serve a script, preferring the user's override if there is one.
+fn script(name: &str, builtin: &'static str) -> content::JavaScript<String> {
+    content::JavaScript(read_override(name).unwrap_or_else(|| String::from(builtin)))
+}
+
+#[get("/assets/<file..>")]
+fn assets(file: PathBuf) -> Option<NamedFile> {
+    templates_dir().and_then(|dir| NamedFile::open(dir.join("assets").join(file)).ok())
+}
+
+#[get("/clocksync.js")]
+fn clocksyncjs() -> content::JavaScript<String> {
+    script("clocksync.js", include_str!("clocksync.js"))
+}
+
+#[get("/i18n.js")]
+fn i18njs() -> content::JavaScript<String> {
+    script("i18n.js", include_str!("i18n.js"))
+}
+
 #[get("/score")]
-pub fn scoreboard() -> content::Html<&'static str> {
-    content::Html(include_str!("scoreboard.html"))
+pub fn scoreboard() -> PageResult {
+    page("scoreboard.html", include_str!("scoreboard.html"))
 }
 
 #[get("/scoreboard.js")]
-pub fn scoreboardjs() -> &'static str { include_str!("scoreboard.js") }
+pub fn scoreboardjs() -> content::JavaScript<String> {
+    script("scoreboard.js", include_str!("scoreboard.js"))
+}
 
 #[get("/penalties")]
-fn penalties() -> content::Html<&'static str> {
-    content::Html(include_str!("penalties.html"))
+fn penalties() -> PageResult {
+    page("penalties.html", include_str!("penalties.html"))
 }
 
 #[get("/penalties.js")]
-fn penaltiesjs() -> &'static str { include_str!("penalties.js") }
+fn penaltiesjs() -> content::JavaScript<String> {
+    script("penalties.js", include_str!("penalties.js"))
+}
 
 #[get("/mobilejt.js")]
-fn mobilejtjs() -> &'static str { include_str!("mobilejt.js") }
+fn mobilejtjs() -> content::JavaScript<String> {
+    script("mobilejt.js", include_str!("mobilejt.js"))
+}
 
 #[get("/scoresheet")]
-fn scoresheet() -> content::Html<&'static str> {
-    content::Html(include_str!("scoresheet.html"))
+fn scoresheet() -> PageResult {
+    page("scoresheet.html", include_str!("scoresheet.html"))
 }
 
 #[get("/scoresheet.js")]
-fn scoresheetjs() -> &'static str { include_str!("scoresheet.js") }
+fn scoresheetjs() -> content::JavaScript<String> {
+    script("scoresheet.js", include_str!("scoresheet.js"))
+}
+
+
+#[get("/lineups")]
+fn lineups() -> PageResult {
+    page("lineups.html", include_str!("lineups.html"))
+}
 
+#[get("/lineups.js")]
+fn lineupsjs() -> content::JavaScript<String> {
+    script("lineups.js", include_str!("lineups.js"))
+}
+
+#[get("/igrf")]
+fn igrf() -> PageResult {
+    page("igrf.html", include_str!("igrf.html"))
+}
+
+#[get("/igrf.js")]
+fn igrfjs() -> content::JavaScript<String> {
+    script("igrf.js", include_str!("igrf.js"))
+}
+
+#[get("/session")]
+fn session() -> PageResult {
+    page("session.html", include_str!("session.html"))
+}
+
+#[get("/session.js")]
+fn sessionjs() -> content::JavaScript<String> {
+    script("session.js", include_str!("session.js"))
+}
+
+#[get("/sessionpage.js")]
+fn sessionpagejs() -> content::JavaScript<String> {
+    script("sessionpage.js", include_str!("sessionpage.js"))
+}
 
 #[get("/mobilejt")]
-fn mobilejt() -> content::Html<&'static str> {
-    content::Html(include_str!("mobilejt.html"))
+fn mobilejt() -> PageResult {
+    page("mobilejt.html", include_str!("mobilejt.html"))
+}
+
+#[get("/overlay")]
+fn overlay() -> PageResult {
+    page("overlay.html", include_str!("overlay.html"))
+}
+
+#[get("/overlay.js")]
+fn overlayjs() -> content::JavaScript<String> {
+    script("overlay.js", include_str!("overlay.js"))
+}
+
+#[get("/overlay/controls")]
+fn overlaycontrol() -> content::Html<&'static str> {
+    content::Html(include_str!("overlaycontrol.html"))
+}
+
+#[get("/media")]
+fn mediaadmin() -> content::Html<&'static str> {
+    content::Html(include_str!("mediaadmin.html"))
 }
 
 #[derive(Serialize)]
@@ -45,16 +187,24 @@ struct GameInfo<'a> {
     away: &'a str,
 }
 
+#[derive(Serialize)]
+struct RosterChoice {
+    idx: String,
+    name: String,
+}
+
 #[derive(Serialize)]
 struct HomepageState<'a> {
     game_in_progress: bool,
-    rosters: Vec<(String, String)>,
+    rosters: Vec<RosterChoice>,
     game: Option<GameInfo<'a>>,
 }
 
 #[get("/")]
 fn index() -> Result<content::Html<String>, handlebars::RenderError> {
-    let rosters = roster::list_rosters().into_iter().map(|(f, r)| (f, r.name));
+    let mut rosters = roster::list_rosters().into_iter()
+        .map(|(f, r)| RosterChoice { idx: f, name: r.name }).collect::<Vec<_>>();
+    rosters.sort_by(|a, b| a.name.cmp(&b.name));
     let guard = get_game();
     let game = guard.as_ref();
     let gameinfo = game.map(|g| GameInfo { home: g.roster(Team::Home).name.as_str(),
@@ -62,17 +212,65 @@ fn index() -> Result<content::Html<String>, handlebars::RenderError> {
 
     HBS.render("startgame", &HomepageState {
         game_in_progress: game.is_some(),
-        rosters: rosters.collect(),
+        rosters: rosters,
         game: gameinfo,
     } ).map(|s| content::Html(s))
 
 }
 
+#[get("/boxscore")]
+fn boxscore(game: Game) -> Result<content::Html<String>, handlebars::RenderError> {
+    HBS.render("boxscore", &game.stats()).map(|s| content::Html(s))
+}
+
+/// Render one of the built-in Handlebars templates, or the user's
+/// override of it.
+pub fn render<T: Serialize>(name: &str, data: &T)
+                            -> Result<content::Html<String>, handlebars::RenderError> {
+    HBS.render(name, data).map(|s| content::Html(s))
+}
+
+const BUILTIN_TEMPLATES: [(&'static str, &'static str); 5] = [
+    ("startgame", include_str!("startgame.hbs")),
+    ("boxscore", include_str!("boxscore.hbs")),
+    ("archive", include_str!("archive.hbs")),
+    ("archivedgame", include_str!("archivedgame.hbs")),
+    ("report", include_str!("report.hbs")),
+];
+
+/// Pages that can be overridden with a Handlebars template.
+const TEMPLATE_PAGES: [&'static str; 8] = [
+    "scoreboard.html", "penalties.html", "scoresheet.html", "lineups.html", "igrf.html",
+    "mobilejt.html", "overlay.html", "session.html",
+];
+
+/// Check that every template in the templates directory parses, so a
+/// broken one is reported at startup rather than when it's first used.
+pub fn check_templates() -> Result<(), String> {
+    let names = BUILTIN_TEMPLATES.iter().map(|&(name, _)| format!("{}.hbs", name))
+        .chain(TEMPLATE_PAGES.iter().map(|name| String::from(*name)));
+    for name in names {
+        if let Some(source) = read_override(&name) {
+            if let Err(e) = Handlebars::new().register_template_string(&name, source) {
+                return Err(format!("bad template {}: {}", name, e));
+            }
+        }
+    }
+    Ok(())
+}
+
 fn init_templates() -> Handlebars {
     let mut handlebars = Handlebars::new();
-    handlebars.register_template_string("startgame",
-                                        include_str!("startgame.hbs")).unwrap();
-    // ...
+    for &(name, builtin) in BUILTIN_TEMPLATES.iter() {
+        if let Some(source) = read_override(&format!("{}.hbs", name)) {
+            match handlebars.register_template_string(name, source) {
+                Ok(()) => continue,
+                Err(e) => println!("Using the built-in {} template, as {}.hbs is broken: {}",
+                                   name, name, e),
+            }
+        }
+        handlebars.register_template_string(name, builtin).unwrap();
+    }
     handlebars
 }
 
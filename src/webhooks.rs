@@ -0,0 +1,235 @@
+/*! Outgoing webhooks. Each target gets a JSON POST of every event it's
+    interested in, as it comes off the event bus, in order. Failed
+    deliveries are retried a few times with backoff, and every attempt is
+    recorded in a delivery log that can be inspected from `/webhooks/log`.
+
+    Targets are kept in `webhooks.json` in the data directory so they
+    survive a restart.
+ */
+
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use std::sync::mpsc::{channel, Sender};
+use std::thread;
+use std::time::Duration;
+
+use hyper;
+use hyper::header::ContentType;
+use hyper::net::HttpsConnector;
+use hyper_native_tls::NativeTlsClient;
+use rocket_contrib::Json;
+use serde_json;
+
+use config;
+use error::{ApiError, ApiResult};
+use events::{self, Notice};
+
+/// How many times to try delivering an event before giving up.
+const MAX_ATTEMPTS: u32 = 4;
+const LOG_LEN: usize = 200;
+
+/// The events a webhook can ask for.
+const WEBHOOK_EVENTS: [&'static str; 7] =
+    ["jam_ended", "score_changed", "timeout", "timeout_ended",
+     "review_outcome", "period_ended", "game_over"];
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Target {
+    pub id: u32,
+    pub url: String,
+    /// Which events to send. Empty means all of them.
+    pub events: Vec<String>,
+}
+
+impl Target {
+    fn wants(&self, name: &str) -> bool {
+        WEBHOOK_EVENTS.contains(&name) &&
+            (self.events.is_empty() || self.events.iter().any(|e| e == name))
+    }
+}
+
+#[derive(Deserialize)]
+struct NewTarget {
+    url: String,
+    #[serde(default)]
+    events: Vec<String>,
+}
+
+#[derive(Serialize, Clone)]
+struct Delivery {
+    target: u32,
+    url: String,
+    seq: u64,
+    event: &'static str,
+    attempt: u32,
+    time: u64,
+    /// The HTTP status we got back, if we got that far.
+    status: Option<u16>,
+    error: Option<String>,
+}
+
+fn targets_file() -> PathBuf {
+    config::get().data_dir.join("webhooks.json")
+}
+
+fn load_targets() -> Vec<Target> {
+    let mut contents = String::new();
+    let read = File::open(targets_file()).and_then(|mut f| f.read_to_string(&mut contents));
+    if read.is_err() {
+        return vec!();
+    }
+    match serde_json::from_str(&contents) {
+        Ok(targets) => targets,
+        Err(e) => {
+            println!("Ignoring bad {}: {}", targets_file().display(), e);
+            vec!()
+        }
+    }
+}
+
+fn save_targets(targets: &[Target]) -> io::Result<()> {
+    let file = File::create(targets_file())?;
+    serde_json::to_writer_pretty(file, targets).map_err(|e| {
+        io::Error::new(io::ErrorKind::Other, e)
+    })
+}
+
+fn client() -> Result<hyper::Client, String> {
+    let ssl = NativeTlsClient::new().map_err(|e| format!("{}", e))?;
+    let mut client = hyper::Client::with_connector(HttpsConnector::new(ssl));
+    client.set_read_timeout(Some(Duration::new(10, 0)));
+    client.set_write_timeout(Some(Duration::new(10, 0)));
+    Ok(client)
+}
+
+fn log_delivery(delivery: Delivery) {
+    let mut log = LOG.lock().unwrap();
+    if log.len() == LOG_LEN {
+        log.pop_front();
+    }
+    log.push_back(delivery);
+}
+
+/// Deliver one event to one target, retrying with exponential backoff.
+fn deliver(client: &hyper::Client, target: &Target, notice: &Notice, body: &str) {
+    for attempt in 1..MAX_ATTEMPTS + 1 {
+        let res = client.post(target.url.as_str())
+            .header(ContentType::json())
+            .body(body)
+            .send();
+        let (status, error) = match res {
+            Ok(ref r) if r.status.is_success() => (Some(r.status.to_u16()), None),
+            Ok(ref r) => (Some(r.status.to_u16()), Some(format!("{}", r.status))),
+            Err(e) => (None, Some(format!("{}", e))),
+        };
+        let failed = error.is_some();
+        log_delivery(Delivery {
+            target: target.id, url: target.url.clone(), seq: notice.seq,
+            event: notice.event.name(), attempt: attempt,
+            time: events::now_millis(), status: status, error: error,
+        });
+        if !failed {
+            return;
+        }
+        if attempt < MAX_ATTEMPTS {
+            thread::sleep(Duration::new(1 << (attempt - 1), 0));
+        }
+    }
+}
+
+struct Job {
+    target: Target,
+    notice: Notice,
+    body: String,
+}
+
+/// Start a thread that delivers one target's events one at a time, so
+/// they arrive in order and a slow target doesn't hold up the others.
+fn spawn_worker(client: Arc<hyper::Client>) -> Sender<Job> {
+    let (tx, rx) = channel::<Job>();
+    thread::spawn(move || {
+        for job in rx {
+            deliver(&client, &job.target, &job.notice, &job.body);
+        }
+    });
+    tx
+}
+
+/// Start the thread that sends events off to the webhook targets.
+pub fn spawn_dispatcher() {
+    let client = match client() {
+        Ok(c) => Arc::new(c),
+        Err(e) => {
+            println!("Webhooks are off, as there's no TLS client: {}", e);
+            return;
+        }
+    };
+    let rx = events::subscribe();
+    thread::spawn(move || {
+        let mut workers: HashMap<u32, Sender<Job>> = HashMap::new();
+        for notice in rx {
+            let name = notice.event.name();
+            let targets = TARGETS.read().unwrap().clone();
+            // Let the workers for deleted targets finish up and stop.
+            workers.retain(|id, _| targets.iter().any(|t| t.id == *id));
+            let wanted = targets.into_iter().filter(|t| t.wants(name)).collect::<Vec<_>>();
+            if wanted.is_empty() {
+                continue;
+            }
+            let body = serde_json::to_string(&notice).unwrap();
+            for target in wanted {
+                let worker = workers.entry(target.id)
+                    .or_insert_with(|| spawn_worker(client.clone()));
+                let _ = worker.send(Job { target: target, notice: notice.clone(),
+                                          body: body.clone() });
+            }
+        }
+    });
+}
+
+#[get("/webhooks")]
+fn get_targets() -> Json<Vec<Target>> {
+    Json(TARGETS.read().unwrap().clone())
+}
+
+#[post("/webhooks", format = "application/json", data = "<target>")]
+fn add_target(target: Json<NewTarget>) -> ApiResult<Json<Vec<Target>>> {
+    let target = target.0;
+    if !target.url.starts_with("http://") && !target.url.starts_with("https://") {
+        return Err(ApiError::bad_request("bad_url", format!("Bad webhook URL {:?}", target.url)));
+    }
+    if let Some(e) = target.events.iter().find(|e| !WEBHOOK_EVENTS.contains(&e.as_str())) {
+        return Err(ApiError::bad_request("bad_event", format!("Unknown event {:?}", e)));
+    }
+    let mut targets = TARGETS.write().unwrap();
+    let id = targets.iter().map(|t| t.id + 1).max().unwrap_or(1);
+    // Only take on the new list once it's saved, so that a target that
+    // couldn't be saved doesn't get events until the next restart.
+    let mut updated = targets.clone();
+    updated.push(Target { id: id, url: target.url, events: target.events });
+    save_targets(&updated)?;
+    *targets = updated;
+    Ok(Json(targets.clone()))
+}
+
+#[delete("/webhooks/<id>")]
+fn delete_target(id: u32) -> ApiResult<Json<Vec<Target>>> {
+    let mut targets = TARGETS.write().unwrap();
+    let updated = targets.iter().filter(|t| t.id != id).cloned().collect::<Vec<_>>();
+    save_targets(&updated)?;
+    *targets = updated;
+    Ok(Json(targets.clone()))
+}
+
+#[get("/webhooks/log")]
+fn get_log() -> Json<Vec<Delivery>> {
+    Json(LOG.lock().unwrap().iter().cloned().collect())
+}
+
+lazy_static! {
+    static ref TARGETS: RwLock<Vec<Target>> = RwLock::new(load_targets());
+    static ref LOG: Mutex<VecDeque<Delivery>> = Mutex::new(VecDeque::new());
+}